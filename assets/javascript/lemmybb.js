@@ -0,0 +1,18 @@
+/* Collapse and expand replies in threaded topic view */
+$(".toggle-replies").on("click", function (event) {
+  event.preventDefault();
+  var toggle = $(this);
+  var depth = toggle.data("depth");
+  var collapse = !toggle.data("collapsed");
+  toggle
+    .closest(".post")
+    .nextAll(".post")
+    .each(function () {
+      if ($(this).data("depth") <= depth) {
+        return false;
+      }
+      $(this).toggle(!collapse);
+    });
+  toggle.data("collapsed", collapse);
+  toggle.html(collapse ? "[+] Expand replies" : "[&minus;] Collapse replies");
+});
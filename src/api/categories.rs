@@ -1,56 +1,365 @@
 use crate::api::{
-    extra::{get_last_reply_in_community, PostOrComment},
+    community::get_community,
+    last_reply::{get_last_reply_in_community, PostOrComment},
     site::resolve_object,
+    NameOrId,
 };
-use anyhow::Error;
+use anyhow::{anyhow, Context, Error};
 use deser_hjson::from_str;
-use futures::future::join_all;
+use futures::future::{join_all, try_join_all};
 use lemmy_api_common::{
+    community::GetCommunityResponse,
+    lemmy_db_schema::newtypes::CommunityId,
     lemmy_db_views_actor::structs::CommunityView,
     sensitive::Sensitive,
-    site::ResolveObjectResponse,
 };
-use std::fs::read_to_string;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{metadata, read_to_string, rename, write},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::time::sleep;
 
 pub static CATEGORIES_FILE: &str = "lemmybb_categories.hjson";
 
-type CategoriesConfig = Vec<(String, Vec<String>)>;
-type CategoriesConfigParsed = Vec<(String, Vec<(CommunityView, Option<PostOrComment>)>)>;
+/// How often the categories file is checked for changes.
+static RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Longest wait between attempts to resolve categories, while the backend is unavailable.
+static MAX_RETRY_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Category as it is written in the categories file
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CategoryConfig {
+    pub title: String,
+    pub description: Option<String>,
+    /// Categories are sorted by position, those with the same position keep the order from file
+    #[serde(default)]
+    pub position: i32,
+    /// Url of an image which is shown next to the category title
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub collapsed: bool,
+    pub communities: Vec<CommunityConfig>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum CommunityConfig {
+    /// Only community name or url
+    Name(String),
+    /// Community with custom title and description for the frontpage
+    WithOverrides {
+        community: String,
+        title: Option<String>,
+        blurb: Option<String>,
+    },
+}
+
+/// Old format where each category only consists of title and community names
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CategoryConfigCompat {
+    Legacy(String, Vec<String>),
+    Full(CategoryConfig),
+}
+
+impl From<CategoryConfigCompat> for CategoryConfig {
+    fn from(category: CategoryConfigCompat) -> Self {
+        match category {
+            CategoryConfigCompat::Legacy(title, communities) => CategoryConfig {
+                title,
+                description: None,
+                position: 0,
+                icon: None,
+                collapsed: false,
+                communities: communities.into_iter().map(CommunityConfig::Name).collect(),
+            },
+            CategoryConfigCompat::Full(c) => c,
+        }
+    }
+}
+
+impl CommunityConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            CommunityConfig::Name(name) => name,
+            CommunityConfig::WithOverrides { community, .. } => community,
+        }
+    }
+}
+
+/// Category ready for rendering
+#[derive(Serialize, Debug)]
+pub struct Category {
+    title: String,
+    description: Option<String>,
+    icon: Option<String>,
+    collapsed: bool,
+    communities: Vec<CategoryCommunity>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryCommunity {
+    community: CommunityView,
+    title: String,
+    blurb: Option<String>,
+    last_reply: Option<PostOrComment>,
+}
+
+/// Categories from config file, with communities resolved to their ids
+type CategoriesResolved = Vec<(CategoryConfig, Vec<CommunityId>)>;
+
+enum CategoriesState {
+    /// Categories file was not loaded yet
+    Unloaded,
+    /// There is no categories file, so the community list is shown on the frontpage instead
+    Missing,
+    Loaded {
+        categories: Arc<CategoriesResolved>,
+        modified: SystemTime,
+    },
+    Failed {
+        error: String,
+        modified: SystemTime,
+        /// Categories from the last successful load, which are still shown on the frontpage
+        previous: Option<Arc<CategoriesResolved>>,
+        /// Resolve errors may be temporary (eg backend not started yet), so loading is retried at
+        /// this time. Parse errors are only retried once the file is changed.
+        retry_at: Option<Instant>,
+        /// Number of failed attempts in a row for the current file, used for backoff
+        failures: u32,
+    },
+}
+
+/// Error from loading the categories file
+struct LoadError {
+    error: String,
+    modified: SystemTime,
+    retry: bool,
+}
+
+static CATEGORIES: Lazy<RwLock<CategoriesState>> =
+    Lazy::new(|| RwLock::new(CategoriesState::Unloaded));
+
+fn categories_file_modified() -> Option<SystemTime> {
+    metadata(CATEGORIES_FILE).and_then(|m| m.modified()).ok()
+}
 
-fn read_categories_file() -> Result<CategoriesConfig, Error> {
+fn parse_categories(config_str: &str) -> Result<Vec<CategoryConfig>, Error> {
+    let mut categories: Vec<CategoryConfig> = from_str::<Vec<CategoryConfigCompat>>(config_str)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    categories.sort_by_key(|c| c.position);
+    Ok(categories)
+}
+
+fn read_categories_file() -> Result<Vec<CategoryConfig>, Error> {
     let config_str = read_to_string(CATEGORIES_FILE)?;
-    Ok(from_str::<CategoriesConfig>(&config_str)?)
+    parse_categories(&config_str).with_context(|| format!("{CATEGORIES_FILE} is malformed"))
+}
+
+async fn resolve_community(community: &str, category: &str) -> Result<CommunityId, Error> {
+    resolve_object(community.to_string(), None)
+        .await
+        .with_context(|| format!("Failed to resolve {community} in category {category}"))?
+        .community
+        .map(|c| c.community.id)
+        .ok_or_else(|| anyhow!("{community} in category {category} is not a community"))
+}
+
+async fn load_categories() -> Result<CategoriesState, LoadError> {
+    let modified = match categories_file_modified() {
+        Some(m) => m,
+        None => return Ok(CategoriesState::Missing),
+    };
+    let config = match read_categories_file() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e:#}");
+            return Err(LoadError {
+                error: format!("{e:#}"),
+                modified,
+                retry: false,
+            });
+        }
+    };
+    let mut categories = CategoriesResolved::new();
+    for category in config {
+        let ids = try_join_all(
+            category
+                .communities
+                .iter()
+                .map(|c| resolve_community(c.name(), &category.title)),
+        )
+        .await;
+        match ids {
+            Ok(ids) => categories.push((category, ids)),
+            Err(e) => {
+                error!("Failed to load {CATEGORIES_FILE}: {e:#}");
+                return Err(LoadError {
+                    error: format!("{e:#}"),
+                    modified,
+                    retry: true,
+                });
+            }
+        }
+    }
+    info!(
+        "Loaded {} categories from {CATEGORIES_FILE}",
+        categories.len()
+    );
+    Ok(CategoriesState::Loaded {
+        categories: Arc::new(categories),
+        modified,
+    })
+}
+
+/// Wait time before loading is retried, doubled after each failure.
+fn retry_interval(failures: u32) -> Duration {
+    RELOAD_INTERVAL
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(MAX_RETRY_INTERVAL)
+}
+
+/// Read and resolve categories file, then replace the currently used categories. If loading
+/// fails, the previously loaded categories are kept. The frontpage only shows the error if
+/// categories were never loaded successfully.
+pub async fn reload_categories() {
+    let loaded = load_categories().await;
+    let mut state = CATEGORIES.write().unwrap();
+    *state = match loaded {
+        Ok(s) => s,
+        Err(e) => {
+            let (previous, failures) = match &*state {
+                CategoriesState::Loaded { categories, .. } => (Some(categories.clone()), 0),
+                CategoriesState::Failed {
+                    previous,
+                    failures,
+                    modified,
+                    ..
+                } if *modified == e.modified => (previous.clone(), *failures),
+                CategoriesState::Failed { previous, .. } => (previous.clone(), 0),
+                _ => (None, 0),
+            };
+            let failures = failures + 1;
+            CategoriesState::Failed {
+                error: e.error,
+                modified: e.modified,
+                previous,
+                retry_at: e.retry.then(|| Instant::now() + retry_interval(failures)),
+                failures,
+            }
+        }
+    };
+}
+
+/// Error from the last attempt to load the categories file, if it failed
+pub fn categories_error() -> Option<String> {
+    match &*CATEGORIES.read().unwrap() {
+        CategoriesState::Failed { error, .. } => Some(error.clone()),
+        _ => None,
+    }
+}
+
+fn needs_reload() -> bool {
+    let modified = categories_file_modified();
+    match &*CATEGORIES.read().unwrap() {
+        CategoriesState::Unloaded => true,
+        CategoriesState::Missing => modified.is_some(),
+        CategoriesState::Loaded { modified: m, .. } => modified != Some(*m),
+        CategoriesState::Failed {
+            modified: m,
+            retry_at,
+            ..
+        } => retry_at.map(|r| Instant::now() >= r).unwrap_or(false) || modified != Some(*m),
+    }
+}
+
+/// Check categories file for changes in regular intervals, and reload it if necessary.
+pub async fn watch_categories_file() {
+    loop {
+        if needs_reload() {
+            reload_categories().await;
+        }
+        sleep(RELOAD_INTERVAL).await;
+    }
+}
+
+/// Read categories file for editing. Returns empty list if the file doesn't exist yet.
+pub fn read_categories_config() -> Result<Vec<CategoryConfig>, Error> {
+    if categories_file_modified().is_none() {
+        return Ok(vec![]);
+    }
+    read_categories_file()
 }
 
+/// Check that all communities can be resolved, then write categories to file and reload them.
+/// Positions are renumbered according to the order of the list.
+pub async fn save_categories(mut categories: Vec<CategoryConfig>) -> Result<(), Error> {
+    for (i, category) in categories.iter_mut().enumerate() {
+        if category.title.trim().is_empty() {
+            return Err(anyhow!("Category title must not be empty"));
+        }
+        category.position = i as i32;
+        try_join_all(
+            category
+                .communities
+                .iter()
+                .map(|c| resolve_community(c.name(), &category.title)),
+        )
+        .await?;
+    }
+    // hjson is a superset of json, so the file can simply be written as json
+    let tmp_file = format!("{CATEGORIES_FILE}.tmp");
+    write(&tmp_file, serde_json::to_string_pretty(&categories)?)?;
+    rename(tmp_file, CATEGORIES_FILE)?;
+    reload_categories().await;
+    Ok(())
+}
+
+/// Returns None if there is no categories file.
 pub async fn get_categories(
     auth: Option<Sensitive<String>>,
-) -> Result<CategoriesConfigParsed, Error> {
-    let categories_config = read_categories_file()?;
+) -> Result<Option<Vec<Category>>, Error> {
+    // in case the watcher didn't run yet
+    let unloaded = matches!(*CATEGORIES.read().unwrap(), CategoriesState::Unloaded);
+    if unloaded {
+        reload_categories().await;
+    }
+    let categories = match &*CATEGORIES.read().unwrap() {
+        CategoriesState::Unloaded | CategoriesState::Missing => return Ok(None),
+        CategoriesState::Loaded { categories, .. } => categories.clone(),
+        CategoriesState::Failed {
+            previous: Some(categories),
+            ..
+        } => categories.clone(),
+        CategoriesState::Failed { error, .. } => return Err(anyhow!(error.clone())),
+    };
 
-    let mut resolved = CategoriesConfigParsed::new();
-    for (key, communities) in categories_config {
-        // first resolve communities
-        // TODO: this only needs to be done once on startup, then store CommunityId
+    let mut resolved = vec![];
+    for (config, community_ids) in categories.iter() {
         let communities = join_all(
-            communities
+            community_ids
                 .iter()
-                .map(|c| resolve_object(c.to_string(), auth.clone())),
+                .map(|c| get_community(NameOrId::Id(c.0), auth.clone())),
         )
         .await;
-        // handle errors
-        let communities = communities
-            .into_iter()
-            .collect::<Result<Vec<ResolveObjectResponse>, Error>>()?;
-        // extract community from response
+        // handle errors and extract community from response
         let communities: Vec<CommunityView> = communities
             .into_iter()
-            .map(|r| r.community.unwrap())
+            .collect::<Result<Vec<GetCommunityResponse>, Error>>()?
+            .into_iter()
+            .map(|c| c.community_view)
             .collect();
         // fetch last replies in communities
         let last_replies = join_all(
             communities
                 .iter()
-                .map(|c| get_last_reply_in_community(c.community.id, auth.clone())),
+                .map(|c| get_last_reply_in_community(c, auth.clone())),
         )
         .await;
         // handle errors
@@ -58,12 +367,78 @@ pub async fn get_categories(
             .into_iter()
             .collect::<Result<Vec<Option<PostOrComment>>, Error>>()?;
         // merge collections
-        let zipped = communities
+        let communities = communities
             .into_iter()
             .zip(last_replies.into_iter())
+            .zip(config.communities.iter())
+            .map(|((community, last_reply), community_config)| {
+                let (title, blurb) = match community_config {
+                    CommunityConfig::Name(_) => (None, None),
+                    CommunityConfig::WithOverrides { title, blurb, .. } => {
+                        (title.clone(), blurb.clone())
+                    }
+                };
+                CategoryCommunity {
+                    title: title.unwrap_or_else(|| community.community.title.clone()),
+                    blurb: blurb.or_else(|| community.community.description.clone()),
+                    community,
+                    last_reply,
+                }
+            })
             .collect();
-        resolved.push((key, zipped));
+        resolved.push(Category {
+            title: config.title.clone(),
+            description: config.description.clone(),
+            icon: config.icon.clone(),
+            collapsed: config.collapsed,
+            communities,
+        });
     }
 
-    Ok(resolved)
+    Ok(Some(resolved))
+}
+
+#[test]
+fn parse_categories_formats() {
+    let config = r#"
+    [
+      [
+        "General"
+        [
+          "!main@voyager.lemmy.ml"
+        ]
+      ]
+      {
+        title: Open Source
+        description: Free software projects
+        position: -1
+        collapsed: true
+        communities: [
+          "https://lemmy.ml/c/linux"
+          {
+            community: "!rust@lemmy.ml"
+            title: Rust
+            blurb: All about the Rust programming language
+          }
+        ]
+      }
+    ]
+    "#;
+    let categories = parse_categories(config).unwrap();
+    assert_eq!(2, categories.len());
+    let open_source = &categories[0];
+    assert_eq!("Open Source", open_source.title);
+    assert!(open_source.collapsed);
+    assert_eq!("!rust@lemmy.ml", open_source.communities[1].name());
+    let general = &categories[1];
+    assert_eq!("General", general.title);
+    assert_eq!(None, general.description);
+    assert_eq!("!main@voyager.lemmy.ml", general.communities[0].name());
+}
+
+#[test]
+fn retry_interval_backoff() {
+    assert_eq!(RELOAD_INTERVAL, retry_interval(1));
+    assert_eq!(RELOAD_INTERVAL * 4, retry_interval(3));
+    assert_eq!(MAX_RETRY_INTERVAL, retry_interval(100));
 }
@@ -1,18 +1,27 @@
-use crate::api::{get, post, put};
+use crate::{
+    api::{get, post, put},
+    pagination::MAX_PAGE_ITEMS,
+};
 use anyhow::Error;
 use lemmy_api_common::{
     comment::{
         CommentReportResponse,
         CommentResponse,
         CreateComment,
+        CreateCommentLike,
         CreateCommentReport,
+        DeleteComment,
         EditComment,
         GetComment,
         GetComments,
         GetCommentsResponse,
+        ListCommentReports,
+        ListCommentReportsResponse,
+        ResolveCommentReport,
+        SaveComment,
     },
     lemmy_db_schema::{
-        newtypes::{CommentId, CommunityId, PostId},
+        newtypes::{CommentId, CommentReportId, CommunityId, PostId},
         CommentSortType,
         ListingType,
     },
@@ -20,13 +29,18 @@ use lemmy_api_common::{
     sensitive::Sensitive,
 };
 
-pub async fn list_comments(
+/// Top-level comments in a post, in chronological order. Lemmy doesn't paginate tree fetches, so
+/// these are all returned by a single request.
+pub async fn list_root_comments(
     post_id: PostId,
     auth: Option<Sensitive<String>>,
 ) -> Result<Vec<CommentView>, Error> {
     let params = GetComments {
-        sort: Some(CommentSortType::New),
+        sort: Some(CommentSortType::Old),
         post_id: Some(post_id),
+        // depth includes the "0" at the start of comment paths, so this only returns top-level
+        // comments
+        max_depth: Some(1),
         type_: Some(ListingType::All),
         auth,
         ..Default::default()
@@ -34,21 +48,82 @@ pub async fn list_comments(
     let mut comments = get::<GetCommentsResponse, GetComments>("/comment/list", &params)
         .await?
         .comments;
-    // simply ignore deleted/removed comments
-    comments.retain(|c| !c.comment.deleted && !c.comment.removed);
-    // show oldest comments first
-    comments.sort_unstable_by_key(|a| a.comment.published);
+    comments.sort_by_key(|c| (c.comment.published, c.comment.id.0));
     Ok(comments)
 }
 
-pub(in crate::api) async fn list_community_comments(
-    community_id: CommunityId,
+/// All replies below a comment at any depth, in chronological order. Lemmy returns at most
+/// `MAX_PAGE_ITEMS` per request, so pages are fetched until one is not full.
+pub async fn list_comment_replies(
+    comment_id: CommentId,
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<CommentView>, Error> {
+    let mut replies = vec![];
+    for page in 1.. {
+        let params = GetComments {
+            sort: Some(CommentSortType::Old),
+            parent_id: Some(comment_id),
+            page: Some(page),
+            limit: Some(MAX_PAGE_ITEMS.into()),
+            type_: Some(ListingType::All),
+            auth: auth.clone(),
+            ..Default::default()
+        };
+        let comments = get::<GetCommentsResponse, GetComments>("/comment/list", &params)
+            .await?
+            .comments;
+        let is_last_page = (comments.len() as i32) < MAX_PAGE_ITEMS;
+        // the parent comment itself is also returned
+        replies.extend(comments.into_iter().filter(|c| c.comment.id != comment_id));
+        if is_last_page {
+            break;
+        }
+    }
+    Ok(replies)
+}
+
+/// Single page of comments in a post, in chronological order. Deleted and removed comments are
+/// included, so that each comment always stays on the same page.
+pub async fn list_comments_page(
+    post_id: PostId,
+    page: i32,
+    limit: i32,
+    newest_first: bool,
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<CommentView>, Error> {
+    let sort = if newest_first {
+        CommentSortType::New
+    } else {
+        CommentSortType::Old
+    };
+    let params = GetComments {
+        sort: Some(sort),
+        post_id: Some(post_id),
+        page: Some(page.into()),
+        limit: Some(limit.into()),
+        type_: Some(ListingType::All),
+        auth,
+        ..Default::default()
+    };
+    Ok(
+        get::<GetCommentsResponse, GetComments>("/comment/list", &params)
+            .await?
+            .comments,
+    )
+}
+
+pub(crate) async fn list_newest_comments(
+    post_id: Option<PostId>,
+    community_id: Option<CommunityId>,
+    limit: i64,
     auth: Option<Sensitive<String>>,
 ) -> Result<GetCommentsResponse, Error> {
     let params = GetComments {
         sort: Some(CommentSortType::New),
-        limit: Some(1),
-        community_id: Some(community_id),
+        limit: Some(limit),
+        post_id,
+        community_id,
+        type_: Some(ListingType::All),
         auth,
         ..Default::default()
     };
@@ -85,6 +160,19 @@ pub async fn edit_comment(
     put("/comment", &params).await
 }
 
+pub async fn delete_comment(
+    comment_id: i32,
+    deleted: bool,
+    auth: Sensitive<String>,
+) -> Result<CommentResponse, Error> {
+    let params = DeleteComment {
+        comment_id: CommentId(comment_id),
+        deleted,
+        auth,
+    };
+    post("/comment/delete", &params).await
+}
+
 pub async fn get_comment(
     comment_id: i32,
     auth: Option<Sensitive<String>>,
@@ -96,6 +184,33 @@ pub async fn get_comment(
     get("/comment", &params).await
 }
 
+/// Score is 1 for upvote, -1 for downvote and 0 to remove the vote
+pub async fn like_comment(
+    comment_id: i32,
+    score: i16,
+    auth: Sensitive<String>,
+) -> Result<CommentResponse, Error> {
+    let params = CreateCommentLike {
+        comment_id: CommentId(comment_id),
+        score,
+        auth,
+    };
+    post("/comment/like", &params).await
+}
+
+pub async fn save_comment(
+    comment_id: i32,
+    save: bool,
+    auth: Sensitive<String>,
+) -> Result<CommentResponse, Error> {
+    let params = SaveComment {
+        comment_id: CommentId(comment_id),
+        save,
+        auth,
+    };
+    put("/comment/save", &params).await
+}
+
 pub async fn report_comment(
     comment_id: i32,
     reason: String,
@@ -108,3 +223,32 @@ pub async fn report_comment(
     };
     post("/comment/report", &params).await
 }
+
+pub async fn list_comment_reports(
+    page: i32,
+    limit: i32,
+    unresolved_only: bool,
+    auth: Sensitive<String>,
+) -> Result<ListCommentReportsResponse, Error> {
+    let params = ListCommentReports {
+        page: Some(page.into()),
+        limit: Some(limit.into()),
+        unresolved_only: Some(unresolved_only),
+        community_id: None,
+        auth,
+    };
+    get("/comment/report/list", &params).await
+}
+
+pub async fn resolve_comment_report(
+    report_id: i32,
+    resolved: bool,
+    auth: Sensitive<String>,
+) -> Result<CommentReportResponse, Error> {
+    let params = ResolveCommentReport {
+        report_id: CommentReportId(report_id),
+        resolved,
+        auth,
+    };
+    put("/comment/report/resolve", &params).await
+}
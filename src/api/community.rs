@@ -1,33 +1,42 @@
-use crate::{
-    api::{get, post, NameOrId},
-    pagination::PAGE_ITEMS,
-};
+use crate::api::{get, post, put, NameOrId};
 use anyhow::Error;
 use lemmy_api_common::{
     community::{
+        AddModToCommunity,
+        AddModToCommunityResponse,
+        BanFromCommunity,
+        BanFromCommunityResponse,
         CommunityResponse,
         CreateCommunity,
         DeleteCommunity,
+        EditCommunity,
         FollowCommunity,
         GetCommunity,
         GetCommunityResponse,
+        HideCommunity,
         ListCommunities,
         ListCommunitiesResponse,
+        TransferCommunity,
+    },
+    lemmy_db_schema::{
+        newtypes::{CommunityId, PersonId},
+        ListingType,
+        SortType,
     },
-    lemmy_db_schema::{newtypes::CommunityId, ListingType, SortType},
     sensitive::Sensitive,
 };
 
 pub async fn list_communities(
     listing_type: ListingType,
     page: Option<i32>,
+    limit: i32,
     auth: Option<Sensitive<String>>,
 ) -> Result<ListCommunitiesResponse, Error> {
     let params = ListCommunities {
         type_: Some(listing_type),
         sort: Some(SortType::NewComments),
         page: page.map(Into::into),
-        limit: Some(PAGE_ITEMS.into()),
+        limit: Some(limit.into()),
         auth,
     };
     get("/community/list", &params).await
@@ -61,29 +70,89 @@ pub async fn follow_community(
     post("/community/follow", &params).await
 }
 
-#[allow(dead_code)]
-pub async fn create_community(
-    name: String,
-    auth: Sensitive<String>,
-) -> Result<CommunityResponse, Error> {
-    let params = CreateCommunity {
-        name: name.clone(),
-        title: name,
-        auth,
-        ..Default::default()
-    };
+pub async fn create_community(params: CreateCommunity) -> Result<CommunityResponse, Error> {
     post("/community", &params).await
 }
 
-#[allow(dead_code)]
+pub async fn edit_community(params: EditCommunity) -> Result<CommunityResponse, Error> {
+    put("/community", &params).await
+}
+
 pub async fn delete_community(
     community_id: CommunityId,
+    deleted: bool,
     auth: Sensitive<String>,
 ) -> Result<CommunityResponse, Error> {
     let params = DeleteCommunity {
         community_id,
+        deleted,
         auth,
-        ..Default::default()
     };
     post("/community/delete", &params).await
 }
+
+/// Hide community from community listings, only allowed for admins
+pub async fn hide_community(
+    community_id: CommunityId,
+    hidden: bool,
+    reason: Option<String>,
+    auth: Sensitive<String>,
+) -> Result<CommunityResponse, Error> {
+    let params = HideCommunity {
+        community_id,
+        hidden,
+        reason,
+        auth,
+    };
+    put("/community/hide", &params).await
+}
+
+pub async fn ban_from_community(
+    community_id: CommunityId,
+    person_id: PersonId,
+    ban: bool,
+    reason: Option<String>,
+    remove_data: bool,
+    expires: Option<i64>,
+    auth: Sensitive<String>,
+) -> Result<BanFromCommunityResponse, Error> {
+    let params = BanFromCommunity {
+        community_id,
+        person_id,
+        ban,
+        remove_data: Some(remove_data),
+        reason,
+        expires,
+        auth,
+    };
+    post("/community/ban_user", &params).await
+}
+
+pub async fn add_mod_to_community(
+    community_id: CommunityId,
+    person_id: PersonId,
+    added: bool,
+    auth: Sensitive<String>,
+) -> Result<AddModToCommunityResponse, Error> {
+    let params = AddModToCommunity {
+        community_id,
+        person_id,
+        added,
+        auth,
+    };
+    post("/community/mod", &params).await
+}
+
+/// Make the given moderator the top moderator (owner) of the community
+pub async fn transfer_community(
+    community_id: CommunityId,
+    person_id: PersonId,
+    auth: Sensitive<String>,
+) -> Result<GetCommunityResponse, Error> {
+    let params = TransferCommunity {
+        community_id,
+        person_id,
+        auth,
+    };
+    post("/community/transfer", &params).await
+}
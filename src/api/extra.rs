@@ -1,133 +1,36 @@
-use crate::{
-    api::{
-        comment::{list_comments, list_community_comments},
-        post::{get_post, list_posts},
-        user::{get_person, list_mentions, list_replies},
-        NameOrId,
-    },
-    env::increased_rate_limit,
+use crate::api::{
+    private_message::list_private_messages,
+    user::{list_mentions, list_replies},
 };
 use anyhow::Error;
 use chrono::NaiveDateTime;
-use futures::future::{join, join_all};
+use futures::future::join;
 use lemmy_api_common::{
-    comment::GetCommentsResponse,
-    lemmy_db_schema::{
-        newtypes::{CommunityId, PostId},
-        source::person::PersonSafe,
-    },
-    lemmy_db_views::structs::{CommentView, PostView},
-    post::GetPostsResponse,
+    lemmy_db_schema::{newtypes::PersonId, source::person::PersonSafe},
     sensitive::Sensitive,
 };
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 
-#[derive(Serialize, Debug)]
-pub struct PostOrComment {
-    title: String,
-    creator: PersonSafe,
-    post_id: PostId,
-    reply_id: i32,
-    time: NaiveDateTime,
-}
-
-fn generate_comment_title(post_title: &str) -> String {
-    format!("Re: {post_title}")
-}
-
-pub async fn get_last_reply_in_thread(
-    post: &PostView,
-    auth: Option<Sensitive<String>>,
-) -> Result<PostOrComment, Error> {
-    if post.counts.comments == 0 {
-        Ok(PostOrComment {
-            title: post.post.name.clone(),
-            creator: post.creator.clone(),
-            post_id: post.post.id,
-            reply_id: post.post.id.0,
-            time: post.post.published,
-        })
-    } else {
-        let post = get_post(post.post.id.0, auth.clone()).await?;
-        let comments: Vec<CommentView> = list_comments(post.post_view.post.id, auth.clone())
-            .await?
-            .into_iter()
-            .filter(|c| !c.comment.deleted && !c.comment.removed)
-            .collect();
-        let creator_id = comments.last().unwrap().comment.creator_id;
-        let creator = get_person(NameOrId::Id(creator_id.0), auth).await?;
-        let last_comment = &comments.last().unwrap().comment;
-        Ok(PostOrComment {
-            title: generate_comment_title(&post.post_view.post.name),
-            creator: creator.person_view.person,
-            post_id: post.post_view.post.id,
-            reply_id: last_comment.id.0,
-            time: last_comment.published,
-        })
-    }
-}
+/// Maximum number of mentions and replies which are shown in the header
+pub static HEADER_NOTIFICATIONS: i32 = 10;
 
-pub async fn get_last_reply_in_community(
-    community_id: CommunityId,
-    auth: Option<Sensitive<String>>,
-) -> Result<Option<PostOrComment>, Error> {
-    if !increased_rate_limit() {
-        return Ok(None);
-    }
-    let (comment, post) = join(
-        list_community_comments(community_id, auth.clone()),
-        list_posts(community_id.0, 1, 1, auth.clone()),
-    )
-    .await;
-    let (comment, post): (GetCommentsResponse, GetPostsResponse) = (comment?, post?);
-    let comment = join_all(
-        comment
-            .comments
-            .iter()
-            .filter(|c| !c.comment.deleted && !c.comment.removed)
-            .last()
-            .map(|c| async {
-                PostOrComment {
-                    title: generate_comment_title(&c.post.name),
-                    creator: c.creator.clone(),
-                    post_id: c.post.id,
-                    reply_id: c.comment.id.0,
-                    time: c.comment.published,
-                }
-            }),
-    )
-    .await
-    .pop();
-    let post = post
-        .posts
-        .iter()
-        .filter(|p| !p.post.deleted && !p.post.removed)
-        .last()
-        .map(|p| PostOrComment {
-            title: p.post.name.clone(),
-            creator: p.creator.clone(),
-            post_id: p.post.id,
-            reply_id: p.post.id.0,
-            time: p.post.published,
-        });
-    // return data for post or comment, depending which is newer
-    Ok(if let Some(comment) = comment {
-        if let Some(post) = post {
-            if post.time > comment.time {
-                Some(post)
-            } else {
-                Some(comment)
-            }
-        } else {
-            Some(comment)
-        }
-    } else {
-        post
-    })
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum NotificationKind {
+    Mention,
+    Reply,
+    PrivateMessage,
+    /// New reply in a topic which the user subscribed to
+    Subscription,
 }
 
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub struct Notification {
+    pub kind: NotificationKind,
+    /// Id of the mention, reply or private message, which is needed to mark it as read. For
+    /// subscriptions this is the comment id.
+    pub id: i32,
+    pub read: bool,
     pub title: String,
     pub from_user: PersonSafe,
     pub reference: String,
@@ -135,35 +38,93 @@ pub struct Notification {
     pub link: String,
 }
 
-/// combine all types of notifications in a single "api call"
-pub async fn get_notifications(auth: Sensitive<String>) -> Result<Vec<Notification>, Error> {
-    let (m, r) = join(list_mentions(auth.clone()), list_replies(auth.clone())).await;
-    // TODO: would be good if we can find out the comment's position in the topic, and link like
-    //       viewtopic?t=1#p2
+async fn mentions_and_replies(
+    unread_only: bool,
+    page: i32,
+    limit: i32,
+    auth: Sensitive<String>,
+) -> Result<(Vec<Notification>, usize), Error> {
+    let (m, r) = join(
+        list_mentions(unread_only, page, limit, auth.clone()),
+        list_replies(unread_only, page, limit, auth.clone()),
+    )
+    .await;
     let mentions: Vec<Notification> = m?
         .mentions
         .into_iter()
         .map(|m| Notification {
+            kind: NotificationKind::Mention,
+            id: m.person_mention.id.0,
+            read: m.person_mention.read,
             title: "Mention".to_string(),
             from_user: m.creator,
             reference: m.comment.content,
             time: m.comment.published,
-            link: format!("/view_topic?t={}", m.post.id),
+            link: format!("/viewtopic?p={}", m.comment.id.0),
         })
         .collect();
-    let mut replies = r?
+    let mut replies: Vec<Notification> = r?
         .replies
         .into_iter()
         .map(|r| Notification {
+            kind: NotificationKind::Reply,
+            id: r.comment_reply.id.0,
+            read: r.comment_reply.read,
             title: "Reply".to_string(),
             from_user: r.creator,
             reference: r.comment.content,
             time: r.comment.published,
-            link: format!("/view_topic?t={}", r.post.id),
+            link: format!("/viewtopic?p={}", r.comment.id.0),
         })
         .collect();
+    let fetched_count = mentions.len().max(replies.len());
     let mut notifications = mentions;
     notifications.append(&mut replies);
+    Ok((notifications, fetched_count))
+}
+
+/// Unread mentions and replies for the header, combined in a single "api call"
+pub async fn get_notifications(auth: Sensitive<String>) -> Result<Vec<Notification>, Error> {
+    let (mut notifications, _) = mentions_and_replies(true, 1, HEADER_NOTIFICATIONS, auth).await?;
     notifications.sort_by_key(|n| n.time);
     Ok(notifications)
 }
+
+/// Mentions, replies and private messages for the notifications page, newest first. Also returns
+/// the highest number of items fetched from a single api endpoint, for pagination. Each type is
+/// paginated separately by Lemmy, so a page contains up to `limit` items of each type, and the
+/// order is only exact within a page.
+pub async fn list_notifications(
+    person_id: PersonId,
+    unread_only: bool,
+    page: i32,
+    limit: i32,
+    auth: Sensitive<String>,
+) -> Result<(Vec<Notification>, usize), Error> {
+    let (n, pm) = join(
+        mentions_and_replies(unread_only, page, limit, auth.clone()),
+        list_private_messages(unread_only, Some(page), Some(limit), auth),
+    )
+    .await;
+    let (mut notifications, fetched_count) = n?;
+    let private_messages = pm?.private_messages;
+    let fetched_count = fetched_count.max(private_messages.len());
+    notifications.extend(
+        private_messages
+            .into_iter()
+            // messages sent by the user are also listed
+            .filter(|pm| pm.creator.id != person_id)
+            .map(|pm| Notification {
+                kind: NotificationKind::PrivateMessage,
+                id: pm.private_message.id.0,
+                read: pm.private_message.read,
+                title: "Private message".to_string(),
+                link: format!("/private_messages_thread?u={}", pm.creator.id.0),
+                from_user: pm.creator,
+                reference: pm.private_message.content,
+                time: pm.private_message.published,
+            }),
+    );
+    notifications.sort_by_key(|n| Reverse(n.time));
+    Ok((notifications, fetched_count))
+}
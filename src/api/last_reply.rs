@@ -0,0 +1,426 @@
+use crate::api::{comment::list_newest_comments, post::list_newest_posts};
+use anyhow::Error;
+use chrono::NaiveDateTime;
+use futures::future::{join, join_all, try_join_all};
+use itertools::Itertools;
+use lemmy_api_common::{
+    lemmy_db_schema::{
+        newtypes::{CommentId, CommunityId, PostId},
+        source::{person::PersonSafe, post::Post},
+    },
+    lemmy_db_views::structs::{CommentView, PostView},
+    lemmy_db_views_actor::structs::CommunityView,
+    sensitive::Sensitive,
+};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+/// How often the newest posts and comments are fetched to update the index.
+static POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of posts and comments which are fetched on each poll.
+static POLL_ITEMS: i64 = 50;
+
+/// Entries older than this are dropped from the index, so that it doesn't grow indefinitely with
+/// federated content, and so that replies which were deleted or removed later are not shown
+/// forever.
+static MAX_ENTRY_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Number of comments fetched when a thread is missing from the index. More than one in case the
+/// newest comments are deleted.
+static THREAD_FETCH_ITEMS: i64 = 10;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PostOrComment {
+    pub title: String,
+    pub creator: PersonSafe,
+    pub post_id: PostId,
+    pub reply_id: i32,
+    /// None if this is a post
+    pub comment_id: Option<CommentId>,
+    pub time: NaiveDateTime,
+}
+
+impl PostOrComment {
+    fn from_post(post: &Post, creator: &PersonSafe) -> Self {
+        PostOrComment {
+            title: post.name.clone(),
+            creator: creator.clone(),
+            post_id: post.id,
+            reply_id: post.id.0,
+            comment_id: None,
+            time: post.published,
+        }
+    }
+
+    fn from_comment(comment: &CommentView) -> Self {
+        PostOrComment {
+            title: generate_comment_title(&comment.post.name),
+            creator: comment.creator.clone(),
+            post_id: comment.post.id,
+            reply_id: comment.comment.id.0,
+            comment_id: Some(comment.comment.id),
+            time: comment.comment.published,
+        }
+    }
+}
+
+fn generate_comment_title(post_title: &str) -> String {
+    format!("Re: {post_title}")
+}
+
+struct ThreadEntry {
+    reply: PostOrComment,
+    /// Value of `newest_comment_time` from post aggregates when this entry was written. If the
+    /// post has a newer value, the entry is outdated.
+    newest_comment_time: NaiveDateTime,
+    inserted: Instant,
+}
+
+struct CommunityEntry {
+    reply: PostOrComment,
+    /// Number of posts and comments from community aggregates when this entry was fetched. If the
+    /// community has different numbers, there was activity which may be missing from the entry.
+    /// None for entries from polling or new content, these are checked once on next access.
+    counts: Option<(i64, i64)>,
+    inserted: Instant,
+}
+
+/// Last activity per thread and per forum, so that they don't need to be fetched from the backend
+/// for every row which is rendered.
+#[derive(Default)]
+struct LastReplyIndex {
+    threads: HashMap<PostId, ThreadEntry>,
+    communities: HashMap<CommunityId, CommunityEntry>,
+}
+
+impl LastReplyIndex {
+    /// Store new activity, unless the existing entries are already newer.
+    fn insert(&mut self, community_id: CommunityId, reply: PostOrComment) {
+        let thread_outdated = self
+            .threads
+            .get(&reply.post_id)
+            .map(|t| t.reply.time < reply.time)
+            .unwrap_or(true);
+        if thread_outdated {
+            self.threads.insert(
+                reply.post_id,
+                ThreadEntry {
+                    newest_comment_time: reply.time,
+                    reply: reply.clone(),
+                    inserted: Instant::now(),
+                },
+            );
+        }
+        let community_outdated = self
+            .communities
+            .get(&community_id)
+            .map(|c| c.reply.time < reply.time)
+            .unwrap_or(true);
+        if community_outdated {
+            self.communities.insert(
+                community_id,
+                CommunityEntry {
+                    reply,
+                    counts: None,
+                    inserted: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Remove entries which show the given post or comment, because it was deleted or removed.
+    /// For a post this removes all entries of the thread.
+    fn remove(&mut self, item: &PostOrComment) {
+        let matches = |r: &PostOrComment| {
+            r.post_id == item.post_id
+                && (item.comment_id.is_none() || r.comment_id == item.comment_id)
+        };
+        self.threads.retain(|_, t| !matches(&t.reply));
+        self.communities.retain(|_, c| !matches(&c.reply));
+    }
+
+    fn remove_outdated(&mut self) {
+        self.threads
+            .retain(|_, t| t.inserted.elapsed() < MAX_ENTRY_AGE);
+        self.communities
+            .retain(|_, c| c.inserted.elapsed() < MAX_ENTRY_AGE);
+    }
+}
+
+static LAST_REPLIES: Lazy<RwLock<LastReplyIndex>> = Lazy::new(Default::default);
+
+/// Update index after creating a new post.
+pub fn record_post(post: &PostView) {
+    LAST_REPLIES.write().unwrap().insert(
+        post.community.id,
+        PostOrComment::from_post(&post.post, &post.creator),
+    );
+}
+
+/// Update index after creating a new comment.
+pub fn record_comment(comment: &CommentView) {
+    LAST_REPLIES
+        .write()
+        .unwrap()
+        .insert(comment.community.id, PostOrComment::from_comment(comment));
+}
+
+/// Remove entries for post and community, so that they are fetched again on next access. Needed
+/// when a post is edited or content is removed.
+pub fn invalidate_last_reply(post_id: PostId, community_id: CommunityId) {
+    let mut index = LAST_REPLIES.write().unwrap();
+    index.threads.remove(&post_id);
+    index.communities.remove(&community_id);
+}
+
+fn cached_thread_reply(post: &PostView) -> Option<PostOrComment> {
+    LAST_REPLIES
+        .read()
+        .unwrap()
+        .threads
+        .get(&post.post.id)
+        .filter(|t| t.newest_comment_time >= post.counts.newest_comment_time)
+        .map(|t| t.reply.clone())
+}
+
+fn insert_thread_reply(post: &PostView, reply: PostOrComment) {
+    LAST_REPLIES.write().unwrap().threads.insert(
+        post.post.id,
+        ThreadEntry {
+            reply,
+            newest_comment_time: post.counts.newest_comment_time,
+            inserted: Instant::now(),
+        },
+    );
+}
+
+pub async fn get_last_reply_in_thread(
+    post: &PostView,
+    auth: Option<Sensitive<String>>,
+) -> Result<PostOrComment, Error> {
+    if post.counts.comments == 0 {
+        return Ok(PostOrComment::from_post(&post.post, &post.creator));
+    }
+    if let Some(reply) = cached_thread_reply(post) {
+        return Ok(reply);
+    }
+
+    let reply = list_newest_comments(Some(post.post.id), None, THREAD_FETCH_ITEMS, auth)
+        .await?
+        .comments
+        .iter()
+        .find(|c| !c.comment.deleted && !c.comment.removed)
+        .map(PostOrComment::from_comment)
+        .unwrap_or_else(|| PostOrComment::from_post(&post.post, &post.creator));
+    insert_thread_reply(post, reply.clone());
+    Ok(reply)
+}
+
+/// Last replies for a list of threads. Threads which are missing from the index are first looked
+/// up in the newest comments of their forums, so that a cold index needs one request per forum
+/// instead of one per thread. Only threads which aren't found there are fetched separately.
+pub async fn get_last_replies_in_threads(
+    posts: &[PostView],
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<PostOrComment>, Error> {
+    let missing: Vec<&PostView> = posts
+        .iter()
+        .filter(|p| p.counts.comments > 0 && cached_thread_reply(p).is_none())
+        .collect();
+    let community_ids: Vec<CommunityId> = missing.iter().map(|p| p.community.id).unique().collect();
+    let newest_comments = try_join_all(
+        community_ids
+            .iter()
+            .map(|c| list_newest_comments(None, Some(*c), POLL_ITEMS, auth.clone())),
+    )
+    .await?;
+    // comments are ordered from newest to oldest, so the first one per thread is its newest
+    let mut newest_per_thread: HashMap<PostId, &CommentView> = HashMap::new();
+    for c in newest_comments.iter().flat_map(|c| &c.comments) {
+        newest_per_thread.entry(c.post.id).or_insert(c);
+    }
+    for post in missing {
+        // only usable if it is the comment which aggregates point to, and it is still visible
+        let newest = newest_per_thread.get(&post.post.id).filter(|c| {
+            c.comment.published >= post.counts.newest_comment_time
+                && !c.comment.deleted
+                && !c.comment.removed
+        });
+        if let Some(c) = newest {
+            insert_thread_reply(post, PostOrComment::from_comment(c));
+        }
+    }
+    join_all(
+        posts
+            .iter()
+            .map(|p| get_last_reply_in_thread(p, auth.clone())),
+    )
+    .await
+    .into_iter()
+    .collect()
+}
+
+pub async fn get_last_reply_in_community(
+    community: &CommunityView,
+    auth: Option<Sensitive<String>>,
+) -> Result<Option<PostOrComment>, Error> {
+    let community_id = community.community.id;
+    let counts = (community.counts.posts, community.counts.comments);
+    let cached = LAST_REPLIES
+        .read()
+        .unwrap()
+        .communities
+        .get(&community_id)
+        .filter(|c| c.counts == Some(counts))
+        .map(|c| c.reply.clone());
+    if cached.is_some() {
+        return Ok(cached);
+    }
+
+    let (comments, posts) = join(
+        list_newest_comments(None, Some(community_id), 1, auth.clone()),
+        list_newest_posts(Some(community_id), 1, auth),
+    )
+    .await;
+    let comment = comments?
+        .comments
+        .iter()
+        .find(|c| !c.comment.deleted && !c.comment.removed)
+        .map(PostOrComment::from_comment);
+    let post = posts?
+        .posts
+        .iter()
+        .find(|p| !p.post.deleted && !p.post.removed)
+        .map(|p| PostOrComment::from_post(&p.post, &p.creator));
+    // use post or comment, depending which is newer
+    let reply = match (comment, post) {
+        (Some(comment), Some(post)) if post.time > comment.time => Some(post),
+        (Some(comment), _) => Some(comment),
+        (None, post) => post,
+    };
+    let mut index = LAST_REPLIES.write().unwrap();
+    match &reply {
+        Some(reply) => {
+            index.communities.insert(
+                community_id,
+                CommunityEntry {
+                    reply: reply.clone(),
+                    counts: Some(counts),
+                    inserted: Instant::now(),
+                },
+            );
+        }
+        None => {
+            index.communities.remove(&community_id);
+        }
+    }
+    Ok(reply)
+}
+
+async fn update_last_replies() -> Result<(), Error> {
+    let (comments, posts) = join(
+        list_newest_comments(None, None, POLL_ITEMS, None),
+        list_newest_posts(None, POLL_ITEMS, None),
+    )
+    .await;
+    let (comments, posts) = (comments?, posts?);
+    let mut index = LAST_REPLIES.write().unwrap();
+    index.remove_outdated();
+    for p in &posts.posts {
+        let reply = PostOrComment::from_post(&p.post, &p.creator);
+        if p.post.deleted || p.post.removed {
+            index.remove(&reply);
+        } else {
+            index.insert(p.community.id, reply);
+        }
+    }
+    for c in &comments.comments {
+        let reply = PostOrComment::from_comment(c);
+        if c.comment.deleted || c.comment.removed {
+            index.remove(&reply);
+        } else {
+            index.insert(c.community.id, reply);
+        }
+    }
+    Ok(())
+}
+
+/// Fetch newest posts and comments in regular intervals, to keep the index up to date with
+/// activity from other users and federated instances. Also drops entries which are outdated, or
+/// which show content that was deleted or removed in the meantime.
+pub async fn poll_last_replies() {
+    loop {
+        if let Err(e) = update_last_replies().await {
+            warn!("Failed to update last replies: {e:#}");
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+fn test_reply(post_id: i32, comment_id: Option<i32>, minute: u32) -> PostOrComment {
+    let time = chrono::NaiveDate::from_ymd_opt(2023, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, minute, 0))
+        .unwrap();
+    let creator = serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "name": "alice",
+        "banned": false,
+        "published": time,
+        "actor_id": "https://example.com/u/alice",
+        "local": true,
+        "deleted": false,
+        "inbox_url": "https://example.com/u/alice/inbox",
+        "admin": false,
+        "bot_account": false,
+        "instance_id": 1
+    }))
+    .unwrap();
+    PostOrComment {
+        title: "title".to_string(),
+        creator,
+        post_id: PostId(post_id),
+        reply_id: comment_id.unwrap_or(post_id),
+        comment_id: comment_id.map(CommentId),
+        time,
+    }
+}
+
+#[test]
+fn last_reply_index_keeps_newest() {
+    let community_id = CommunityId(1);
+    let mut index = LastReplyIndex::default();
+    index.insert(community_id, test_reply(1, Some(11), 2));
+    // older activity from polling doesn't replace the entries
+    index.insert(community_id, test_reply(1, Some(10), 1));
+    index.insert(community_id, test_reply(2, None, 0));
+    assert_eq!(
+        Some(CommentId(11)),
+        index.threads[&PostId(1)].reply.comment_id
+    );
+    assert_eq!(PostId(1), index.communities[&community_id].reply.post_id);
+    assert_eq!(None, index.threads[&PostId(2)].reply.comment_id);
+}
+
+#[test]
+fn last_reply_index_remove() {
+    let community_id = CommunityId(1);
+    let mut index = LastReplyIndex::default();
+    index.insert(community_id, test_reply(1, Some(11), 1));
+    index.insert(CommunityId(2), test_reply(2, Some(12), 2));
+    // removing a different comment in the thread keeps the entries
+    index.remove(&test_reply(1, Some(10), 0));
+    assert!(index.threads.contains_key(&PostId(1)));
+    // removing the post removes all entries for the thread
+    index.remove(&test_reply(1, None, 0));
+    assert!(!index.threads.contains_key(&PostId(1)));
+    assert!(!index.communities.contains_key(&community_id));
+    assert!(index.threads.contains_key(&PostId(2)));
+}
@@ -3,6 +3,7 @@ pub mod comment;
 pub mod community;
 pub mod extra;
 pub mod image;
+pub mod last_reply;
 pub mod moderation;
 pub mod post;
 pub mod private_message;
@@ -1,21 +1,29 @@
-use crate::api::{get, post};
+use crate::{
+    api::{get, post},
+    pagination::MAX_PAGE_ITEMS,
+};
 use anyhow::Error;
 use lemmy_api_common::{
     comment::{CommentResponse, RemoveComment},
-    lemmy_db_schema::newtypes::{CommentId, PostId},
-    post::{PostResponse, RemovePost},
+    lemmy_db_schema::{
+        newtypes::{CommentId, CommunityId, PostId},
+        ModlogActionType,
+        PostFeatureType,
+    },
+    post::{FeaturePost, LockPost, PostResponse, RemovePost},
     sensitive::Sensitive,
     site::{GetModlog, GetModlogResponse},
 };
 
 pub async fn remove_post(
     post_id: i32,
+    removed: bool,
     reason: String,
     auth: Sensitive<String>,
 ) -> Result<PostResponse, Error> {
     let params = RemovePost {
         post_id: PostId(post_id),
-        removed: true,
+        removed,
         reason: Some(reason),
         auth,
     };
@@ -24,22 +32,63 @@ pub async fn remove_post(
 
 pub async fn remove_comment(
     comment_id: i32,
+    removed: bool,
     reason: String,
     auth: Sensitive<String>,
 ) -> Result<CommentResponse, Error> {
     let params = RemoveComment {
         comment_id: CommentId(comment_id),
-        removed: true,
+        removed,
         reason: Some(reason),
         auth,
     };
     post("/comment/remove", &params).await
 }
 
-pub async fn get_mod_log(auth: Option<Sensitive<String>>) -> Result<GetModlogResponse, Error> {
+pub async fn lock_post(
+    post_id: i32,
+    locked: bool,
+    auth: Sensitive<String>,
+) -> Result<PostResponse, Error> {
+    let params = LockPost {
+        post_id: PostId(post_id),
+        locked,
+        auth,
+    };
+    post("/post/lock", &params).await
+}
+
+pub async fn feature_post(
+    post_id: i32,
+    featured: bool,
+    feature_type: PostFeatureType,
+    auth: Sensitive<String>,
+) -> Result<PostResponse, Error> {
+    let params = FeaturePost {
+        post_id: PostId(post_id),
+        featured,
+        feature_type,
+        auth,
+    };
+    post("/post/feature", &params).await
+}
+
+pub async fn get_mod_log(params: GetModlog) -> Result<GetModlogResponse, Error> {
+    get("/modlog", &params).await
+}
+
+/// Newest mod log entries of the given type in a community
+pub async fn get_community_mod_log(
+    community_id: CommunityId,
+    type_: ModlogActionType,
+    auth: Option<Sensitive<String>>,
+) -> Result<GetModlogResponse, Error> {
     let params = GetModlog {
+        community_id: Some(community_id),
+        type_: Some(type_),
+        limit: Some(MAX_PAGE_ITEMS.into()),
         auth,
         ..Default::default()
     };
-    get("/modlog", &params).await
+    get_mod_log(params).await
 }
@@ -1,21 +1,30 @@
-use crate::api::{get, post, put};
+use crate::{
+    api::{get, post, put},
+    pagination::MAX_PAGE_ITEMS,
+};
 use anyhow::Error;
 use lemmy_api_common::{
     lemmy_db_schema::{
-        newtypes::{CommunityId, PostId},
+        newtypes::{CommunityId, PostId, PostReportId},
         ListingType,
         SortType,
     },
+    lemmy_db_views::structs::PostView,
     post::{
         CreatePost,
+        CreatePostLike,
         CreatePostReport,
         EditPost,
         GetPost,
         GetPostResponse,
         GetPosts,
         GetPostsResponse,
+        ListPostReports,
+        ListPostReportsResponse,
         PostReportResponse,
         PostResponse,
+        ResolvePostReport,
+        SavePost,
     },
     sensitive::Sensitive,
 };
@@ -24,10 +33,28 @@ pub async fn list_posts(
     community_id: i32,
     limit: i32,
     page: i32,
+    sort: SortType,
     auth: Option<Sensitive<String>>,
 ) -> Result<GetPostsResponse, Error> {
     let params = GetPosts {
         community_id: Some(CommunityId(community_id)),
+        sort: Some(sort),
+        limit: Some(limit.into()),
+        page: Some(page.into()),
+        type_: Some(ListingType::All),
+        auth,
+        ..Default::default()
+    };
+    get("/post/list", &params).await
+}
+
+/// Posts from all forums, with recently active ones first
+pub async fn list_active_posts(
+    page: i32,
+    limit: i32,
+    auth: Option<Sensitive<String>>,
+) -> Result<GetPostsResponse, Error> {
+    let params = GetPosts {
         sort: Some(SortType::NewComments),
         limit: Some(limit.into()),
         page: Some(page.into()),
@@ -38,6 +65,48 @@ pub async fn list_posts(
     get("/post/list", &params).await
 }
 
+/// Posts which are featured in the community, or featured on the site if no community is given.
+pub async fn list_featured_posts(
+    community_id: Option<CommunityId>,
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<PostView>, Error> {
+    let params = GetPosts {
+        community_id,
+        sort: Some(SortType::New),
+        limit: Some(MAX_PAGE_ITEMS.into()),
+        type_: Some(ListingType::All),
+        auth,
+        ..Default::default()
+    };
+    let posts = get::<GetPostsResponse, GetPosts>("/post/list", &params)
+        .await?
+        .posts;
+    // Lemmy always lists featured posts first, so the rest can be ignored
+    Ok(posts
+        .into_iter()
+        .take_while(|p| match community_id {
+            Some(_) => p.post.featured_community,
+            None => p.post.featured_local,
+        })
+        .collect())
+}
+
+pub(in crate::api) async fn list_newest_posts(
+    community_id: Option<CommunityId>,
+    limit: i64,
+    auth: Option<Sensitive<String>>,
+) -> Result<GetPostsResponse, Error> {
+    let params = GetPosts {
+        community_id,
+        sort: Some(SortType::New),
+        limit: Some(limit),
+        type_: Some(ListingType::All),
+        auth,
+        ..Default::default()
+    };
+    get("/post/list", &params).await
+}
+
 pub async fn get_post(id: i32, auth: Option<Sensitive<String>>) -> Result<GetPostResponse, Error> {
     let params = GetPost {
         id: Some(PostId(id)),
@@ -79,6 +148,33 @@ pub async fn edit_post(
     put("/post", &params).await
 }
 
+/// Score is 1 for upvote, -1 for downvote and 0 to remove the vote
+pub async fn like_post(
+    post_id: i32,
+    score: i16,
+    auth: Sensitive<String>,
+) -> Result<PostResponse, Error> {
+    let params = CreatePostLike {
+        post_id: PostId(post_id),
+        score,
+        auth,
+    };
+    post("/post/like", &params).await
+}
+
+pub async fn save_post(
+    post_id: i32,
+    save: bool,
+    auth: Sensitive<String>,
+) -> Result<PostResponse, Error> {
+    let params = SavePost {
+        post_id: PostId(post_id),
+        save,
+        auth,
+    };
+    put("/post/save", &params).await
+}
+
 pub async fn report_post(
     post_id: i32,
     reason: String,
@@ -91,3 +187,32 @@ pub async fn report_post(
     };
     post("/post/report", &params).await
 }
+
+pub async fn list_post_reports(
+    page: i32,
+    limit: i32,
+    unresolved_only: bool,
+    auth: Sensitive<String>,
+) -> Result<ListPostReportsResponse, Error> {
+    let params = ListPostReports {
+        page: Some(page.into()),
+        limit: Some(limit.into()),
+        unresolved_only: Some(unresolved_only),
+        community_id: None,
+        auth,
+    };
+    get("/post/report/list", &params).await
+}
+
+pub async fn resolve_post_report(
+    report_id: i32,
+    resolved: bool,
+    auth: Sensitive<String>,
+) -> Result<PostReportResponse, Error> {
+    let params = ResolvePostReport {
+        report_id: PostReportId(report_id),
+        resolved,
+        auth,
+    };
+    put("/post/report/resolve", &params).await
+}
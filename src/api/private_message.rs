@@ -14,12 +14,15 @@ use lemmy_api_common::{
 
 pub(crate) async fn list_private_messages(
     unread_only: bool,
+    page: Option<i32>,
+    limit: Option<i32>,
     auth: Sensitive<String>,
 ) -> Result<PrivateMessagesResponse, Error> {
     let params = GetPrivateMessages {
         auth,
         unread_only: Some(unread_only),
-        ..Default::default()
+        page: page.map(Into::into),
+        limit: limit.map(Into::into),
     };
     get("/private_message/list", &params).await
 }
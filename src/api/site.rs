@@ -1,11 +1,18 @@
-use crate::api::{get, post};
+use crate::api::{get, post, put};
 use anyhow::Error;
 use futures::future::join;
 use lemmy_api_common::{
     lemmy_db_schema::source::local_site::RegistrationMode,
     sensitive::Sensitive,
     site::{
+        ApproveRegistrationApplication,
         CreateSite,
+        EditSite,
+        GetUnreadRegistrationApplicationCount,
+        GetUnreadRegistrationApplicationCountResponse,
+        ListRegistrationApplications,
+        ListRegistrationApplicationsResponse,
+        RegistrationApplicationResponse,
         ResolveObject,
         ResolveObjectResponse,
         Search,
@@ -30,6 +37,48 @@ pub async fn create_site(
     post("/site", &params).await
 }
 
+pub async fn edit_site(params: EditSite) -> Result<SiteResponse, Error> {
+    put("/site", &params).await
+}
+
+pub async fn list_registration_applications(
+    page: i32,
+    limit: i32,
+    unread_only: bool,
+    auth: Sensitive<String>,
+) -> Result<ListRegistrationApplicationsResponse, Error> {
+    let params = ListRegistrationApplications {
+        unread_only: Some(unread_only),
+        page: Some(page.into()),
+        limit: Some(limit.into()),
+        auth,
+    };
+    get("/admin/registration_application/list", &params).await
+}
+
+pub async fn approve_registration_application(
+    id: i32,
+    approve: bool,
+    deny_reason: Option<String>,
+    auth: Sensitive<String>,
+) -> Result<RegistrationApplicationResponse, Error> {
+    let params = ApproveRegistrationApplication {
+        id,
+        approve,
+        deny_reason,
+        auth,
+    };
+    put("/admin/registration_application/approve", &params).await
+}
+
+/// Number of registration applications which no admin has handled yet
+pub async fn get_registration_application_count(
+    auth: Sensitive<String>,
+) -> Result<GetUnreadRegistrationApplicationCountResponse, Error> {
+    let params = GetUnreadRegistrationApplicationCount { auth };
+    get("/admin/registration_application/count", &params).await
+}
+
 pub async fn search(
     query: String,
     auth: Option<Sensitive<String>>,
@@ -4,11 +4,14 @@ use crate::{
 };
 use anyhow::Error;
 use lemmy_api_common::{
-    lemmy_db_schema::newtypes::PersonId,
+    lemmy_db_schema::newtypes::{CommentReplyId, PersonId, PersonMentionId},
     person::{
         BanPerson,
         BanPersonResponse,
+        BannedPersonsResponse,
         ChangePassword,
+        CommentReplyResponse,
+        GetBannedPersons,
         GetCaptchaResponse,
         GetPersonDetails,
         GetPersonDetailsResponse,
@@ -16,9 +19,16 @@ use lemmy_api_common::{
         GetPersonMentionsResponse,
         GetReplies,
         GetRepliesResponse,
+        GetReportCount,
+        GetReportCountResponse,
+        GetUnreadCount,
+        GetUnreadCountResponse,
         Login,
         LoginResponse,
         MarkAllAsRead,
+        MarkCommentReplyAsRead,
+        MarkPersonMentionAsRead,
+        PersonMentionResponse,
         Register,
         SaveUserSettings,
     },
@@ -40,6 +50,24 @@ pub async fn get_person(
     get("/user", &params).await
 }
 
+/// Posts and comments which the user has saved, newest first
+pub async fn get_saved(
+    person_id: PersonId,
+    page: i32,
+    limit: i32,
+    auth: Sensitive<String>,
+) -> Result<GetPersonDetailsResponse, Error> {
+    let params = GetPersonDetails {
+        person_id: Some(person_id),
+        saved_only: Some(true),
+        page: Some(page.into()),
+        limit: Some(limit.into()),
+        auth: Some(auth),
+        ..Default::default()
+    };
+    get("/user", &params).await
+}
+
 pub async fn login(username_or_email: &str, password: &str) -> Result<LoginResponse, Error> {
     let params = Login {
         username_or_email: Sensitive::new(username_or_email.to_string()),
@@ -68,25 +96,67 @@ pub async fn register(form: RegisterForm) -> Result<LoginResponse, Error> {
 }
 
 pub(crate) async fn list_mentions(
+    unread_only: bool,
+    page: i32,
+    limit: i32,
     auth: Sensitive<String>,
 ) -> Result<GetPersonMentionsResponse, Error> {
     let params = GetPersonMentions {
+        unread_only: Some(unread_only),
+        page: Some(page.into()),
+        limit: Some(limit.into()),
         auth,
-        unread_only: Some(true),
         ..Default::default()
     };
     get("/user/mention", &params).await
 }
 
-pub(crate) async fn list_replies(auth: Sensitive<String>) -> Result<GetRepliesResponse, Error> {
+pub(crate) async fn list_replies(
+    unread_only: bool,
+    page: i32,
+    limit: i32,
+    auth: Sensitive<String>,
+) -> Result<GetRepliesResponse, Error> {
     let params = GetReplies {
+        unread_only: Some(unread_only),
+        page: Some(page.into()),
+        limit: Some(limit.into()),
         auth,
-        unread_only: Some(true),
         ..Default::default()
     };
     get("/user/replies", &params).await
 }
 
+pub(crate) async fn mark_mention_read(
+    person_mention_id: PersonMentionId,
+    auth: Sensitive<String>,
+) -> Result<PersonMentionResponse, Error> {
+    let params = MarkPersonMentionAsRead {
+        person_mention_id,
+        read: true,
+        auth,
+    };
+    post("/user/mention/mark_as_read", &params).await
+}
+
+pub(crate) async fn mark_reply_read(
+    comment_reply_id: CommentReplyId,
+    auth: Sensitive<String>,
+) -> Result<CommentReplyResponse, Error> {
+    let params = MarkCommentReplyAsRead {
+        comment_reply_id,
+        read: true,
+        auth,
+    };
+    post("/comment/mark_as_read", &params).await
+}
+
+/// Number of unread mentions, replies and private messages
+pub async fn get_unread_count(auth: Sensitive<String>) -> Result<GetUnreadCountResponse, Error> {
+    let params = GetUnreadCount { auth };
+    get("/user/unread_count", &params).await
+}
+
 pub async fn mark_all_as_read(auth: Sensitive<String>) -> Result<GetRepliesResponse, Error> {
     let params = MarkAllAsRead { auth };
     post("/user/mark_all_as_read", &params).await
@@ -99,19 +169,38 @@ pub async fn save_settings(params: SaveUserSettings) -> Result<LoginResponse, Er
     put("/user/save_user_settings", &params).await
 }
 
+/// Ban or unban user from the whole site. `expires` is a unix timestamp, or None for a permanent
+/// ban.
 pub async fn ban_user(
     user_id: i32,
-    reason: String,
+    ban: bool,
+    reason: Option<String>,
     remove_data: bool,
+    expires: Option<i64>,
     auth: Sensitive<String>,
 ) -> Result<BanPersonResponse, Error> {
     let params = BanPerson {
         person_id: PersonId(user_id),
-        ban: true,
+        ban,
         remove_data: Some(remove_data),
-        reason: Some(reason),
-        expires: None,
+        reason,
+        expires,
         auth,
     };
     post("/user/ban", &params).await
 }
+
+pub async fn get_banned_persons(auth: Sensitive<String>) -> Result<BannedPersonsResponse, Error> {
+    let params = GetBannedPersons { auth };
+    get("/user/banned", &params).await
+}
+
+/// Number of unresolved reports in communities which the user moderates, or in all communities
+/// for admins
+pub async fn get_report_count(auth: Sensitive<String>) -> Result<GetReportCountResponse, Error> {
+    let params = GetReportCount {
+        community_id: None,
+        auth,
+    };
+    get("/user/report_count", &params).await
+}
@@ -8,10 +8,36 @@ pub fn lemmy_backend() -> String {
     std::env::var("LEMMYBB_BACKEND").unwrap_or_else(|_| "http://localhost:8536".to_string())
 }
 
-/// Set true if Lemmy backend runs with increased message rate limit. This is necessary to show
-/// last replies for threads and forums.
-pub fn increased_rate_limit() -> bool {
-    !std::env::var("LEMMYBB_INCREASED_RATE_LIMIT")
-        .unwrap_or_default()
-        .is_empty()
+use crate::pagination::MAX_PAGE_ITEMS;
+
+/// Default number of topics per page in forums and other topic lists. Users can override this.
+pub fn topics_per_page() -> i32 {
+    page_size("LEMMYBB_TOPICS_PER_PAGE")
+}
+
+/// Default number of posts per page in topics. Users can override this.
+pub fn posts_per_page() -> i32 {
+    page_size("LEMMYBB_POSTS_PER_PAGE")
+}
+
+/// Page size from environment variable, limited to what Lemmy can return in one request.
+fn page_size(var: &str) -> i32 {
+    let value = match std::env::var(var) {
+        Ok(v) => v,
+        Err(_) => return 20,
+    };
+    match value.parse::<i32>() {
+        Ok(n) if (1..=MAX_PAGE_ITEMS).contains(&n) => n,
+        Ok(n) => {
+            warn!(
+                "{var}={n} is out of range, using {}",
+                n.clamp(1, MAX_PAGE_ITEMS)
+            );
+            n.clamp(1, MAX_PAGE_ITEMS)
+        }
+        Err(_) => {
+            warn!("{var}={value} is not a number, using 20");
+            20
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use lemmy_api_common::lemmy_db_schema::SortType;
+use serde::{Deserialize, Serialize};
+
+/// Order of topics in a forum
+#[derive(FromFormField, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ForumSort {
+    #[default]
+    #[field(value = "last_reply")]
+    LastReply,
+    #[field(value = "newest")]
+    Newest,
+    #[field(value = "replies")]
+    Replies,
+    #[field(value = "top_day")]
+    TopDay,
+    #[field(value = "top_week")]
+    TopWeek,
+    #[field(value = "top_month")]
+    TopMonth,
+    #[field(value = "top_year")]
+    TopYear,
+    #[field(value = "top_all")]
+    TopAll,
+}
+
+impl ForumSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            ForumSort::LastReply => "last_reply",
+            ForumSort::Newest => "newest",
+            ForumSort::Replies => "replies",
+            ForumSort::TopDay => "top_day",
+            ForumSort::TopWeek => "top_week",
+            ForumSort::TopMonth => "top_month",
+            ForumSort::TopYear => "top_year",
+            ForumSort::TopAll => "top_all",
+        }
+    }
+
+    pub fn sort_type(self) -> SortType {
+        match self {
+            ForumSort::LastReply => SortType::NewComments,
+            ForumSort::Newest => SortType::New,
+            ForumSort::Replies => SortType::MostComments,
+            ForumSort::TopDay => SortType::TopDay,
+            ForumSort::TopWeek => SortType::TopWeek,
+            ForumSort::TopMonth => SortType::TopMonth,
+            ForumSort::TopYear => SortType::TopYear,
+            ForumSort::TopAll => SortType::TopAll,
+        }
+    }
+}
+
+pub static SORT_OPTIONS: [(ForumSort, &str); 8] = [
+    (ForumSort::LastReply, "Last reply"),
+    (ForumSort::Newest, "Newest topic"),
+    (ForumSort::Replies, "Most replies"),
+    (ForumSort::TopDay, "Top score (day)"),
+    (ForumSort::TopWeek, "Top score (week)"),
+    (ForumSort::TopMonth, "Top score (month)"),
+    (ForumSort::TopYear, "Top score (year)"),
+    (ForumSort::TopAll, "Top score (all time)"),
+];
@@ -6,18 +6,25 @@ extern crate json_gettext;
 mod api;
 mod env;
 mod error;
+mod forum_sort;
 mod pagination;
+mod persist;
+mod read_state;
 mod routes;
 mod site_fairing;
+mod subscriptions;
 mod template_helpers;
 #[cfg(test)]
 mod test;
+mod user_prefs;
 mod utils;
 
 use crate::{
-    api::image::image,
+    api::{categories::watch_categories_file, image::image, last_reply::poll_last_replies},
     env::listen_address,
+    read_state::persist_read_state,
     routes::{
+        admin::*,
         backend_endpoints::*,
         comment::*,
         community::*,
@@ -28,11 +35,13 @@ use crate::{
         user::*,
     },
     site_fairing::SiteFairing,
+    subscriptions::poll_subscriptions,
     template_helpers::*,
 };
 use anyhow::Error;
 use env_logger::Env;
 use rocket::{
+    fairing::AdHoc,
     fs::{relative, FileServer},
     Build,
     Config,
@@ -72,7 +81,6 @@ fn init_rocket() -> Result<Rocket<Build>, Error> {
         reg.register_helper("add", Box::new(add));
         reg.register_helper("sub", Box::new(sub));
         reg.register_helper("mod", Box::new(modulo));
-        reg.register_helper("comment_page", Box::new(comment_page));
         reg.register_helper("length", Box::new(length));
         reg.register_helper("community_actor_id", Box::new(community_actor_id));
         reg.register_helper("user_actor_id", Box::new(user_actor_id));
@@ -95,17 +103,33 @@ fn init_rocket() -> Result<Rocket<Build>, Error> {
         .configure(config)
         .attach(template_fairing)
         .attach(SiteFairing {})
+        .attach(AdHoc::on_liftoff("Background tasks", |_| {
+            Box::pin(async {
+                tokio::spawn(watch_categories_file());
+                tokio::spawn(poll_last_replies());
+                tokio::spawn(persist_read_state());
+                tokio::spawn(poll_subscriptions());
+            })
+        }))
         .mount(
             "/",
             routes![
                 index,
                 view_forum,
                 view_topic,
+                view_comment,
+                vote,
+                bookmarks,
+                bookmark,
+                topic_subscriptions,
+                subscribe,
+                topic_view_mode,
                 login,
                 do_login,
                 post_editor,
                 do_post,
                 comment_editor,
+                multiquote,
                 do_comment,
                 logout,
                 register,
@@ -113,8 +137,11 @@ fn init_rocket() -> Result<Rocket<Build>, Error> {
                 setup,
                 do_setup,
                 mark_all_notifications_read,
+                notifications,
+                mark_notification_read,
                 legal,
                 search,
+                unread,
                 view_profile,
                 private_messages_list,
                 private_messages_thread,
@@ -137,9 +164,32 @@ fn init_rocket() -> Result<Rocket<Build>, Error> {
                 api_site,
                 remove_item,
                 do_remove_item,
+                lock_topic,
+                do_lock_topic,
+                feature_topic,
                 mod_log,
+                report_queue,
+                resolve_report,
+                report_ban,
+                forum_moderators,
+                do_forum_moderators,
+                forum_bans,
+                forum_unban,
                 ban_form,
-                ban
+                ban,
+                edit_categories,
+                do_edit_categories,
+                banned_users,
+                unban_user,
+                site_settings,
+                do_site_settings,
+                registration_applications,
+                handle_application,
+                create_forum,
+                do_create_forum,
+                forum_settings,
+                do_forum_settings,
+                forum_action
             ],
         )
         .mount("/assets", FileServer::from(relative!("assets"))))
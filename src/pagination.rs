@@ -1,6 +1,24 @@
+use lemmy_api_common::{
+    lemmy_db_schema::newtypes::CommentId,
+    lemmy_db_views::structs::CommentView,
+};
 use serde::Serialize;
 
-pub static PAGE_ITEMS: i32 = 20;
+/// Highest number of items per page, because Lemmy doesn't return more than this in one request.
+pub static MAX_PAGE_ITEMS: i32 = 50;
+
+/// Returns the page of the topic on which the comment is shown, or None if it is not in the list.
+/// Comments need to be in the same order as in `view_topic`.
+pub fn comment_page(
+    comment_id: CommentId,
+    comments: &[CommentView],
+    page_size: i32,
+) -> Option<i32> {
+    comments
+        .iter()
+        .position(|c| c.comment.id == comment_id)
+        .map(|i| i as i32 / page_size + 1)
+}
 
 /// need to represent things in a more complicated way, becayse handlebars doesnt support enums
 #[derive(Serialize, Debug, Default)]
@@ -20,8 +38,8 @@ pub struct Pagination {
 pub enum PageLimit {
     // param is index of last page
     Known(i32),
-    // param is the number of items on current page
-    Unknown(usize),
+    // params are the number of items on current page, and the page size
+    Unknown(usize, i32),
 }
 
 impl Pagination {
@@ -52,8 +70,8 @@ impl Pagination {
                 p.after_separator = (last_page - current_page) > 3;
                 p.last_page = last_page;
             }
-            PageLimit::Unknown(current_page_items) => {
-                p.is_last_page = (current_page_items as i32) < PAGE_ITEMS;
+            PageLimit::Unknown(current_page_items, page_size) => {
+                p.is_last_page = (current_page_items as i32) < page_size;
                 if !p.is_last_page {
                     p.after_pages.push(current_page + 1);
                     p.after_pages.push(current_page + 2);
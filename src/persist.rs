@@ -0,0 +1,45 @@
+use anyhow::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs::{read_to_string, rename, write},
+    path::Path,
+    sync::Mutex,
+};
+
+/// Held while writing a file, so that concurrent saves don't use the same temporary file.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Read data from a JSON file. Returns None if the file doesn't exist or can't be parsed. A file
+/// which can't be parsed is renamed, so that it isn't overwritten by the next save and the data
+/// can still be recovered manually.
+pub fn load<T: DeserializeOwned>(file: &str) -> Option<T> {
+    if !Path::new(file).exists() {
+        return None;
+    }
+    let data = read_to_string(file)
+        .map_err(Error::from)
+        .and_then(|s| Ok(serde_json::from_str(&s)?));
+    match data {
+        Ok(d) => Some(d),
+        Err(e) => {
+            let broken_file = format!("{file}.broken");
+            error!("Failed to load {file}, moving it to {broken_file}: {e:#}");
+            if let Err(e) = rename(file, &broken_file) {
+                error!("Failed to move {file}: {e:#}");
+            }
+            None
+        }
+    }
+}
+
+/// Write data to a JSON file. It is written to a temporary file first and then renamed, so that
+/// the file is never left half written. Callers should hold the lock of the data until this
+/// returns, so that an older state can't overwrite a newer one.
+pub fn save<T: Serialize>(file: &str, data: &T) -> Result<(), Error> {
+    let json = serde_json::to_string(data)?;
+    let _lock = WRITE_LOCK.lock().unwrap();
+    let tmp_file = format!("{file}.tmp");
+    write(&tmp_file, json)?;
+    rename(tmp_file, file)?;
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::{api::last_reply::PostOrComment, persist};
+use chrono::{NaiveDateTime, Utc};
+use lemmy_api_common::lemmy_db_schema::newtypes::{CommentId, CommunityId, PersonId, PostId};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+use tokio::time::sleep;
+
+pub static READ_STATE_FILE: &str = "lemmybb_read_state.json";
+
+/// How often changes to read state are written to disk.
+static SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PersonReadState {
+    /// Activity before this time counts as read. This is the time when tracking started for the
+    /// user, so that existing topics are not all shown as unread.
+    since: NaiveDateTime,
+    /// Highest comment id which the user has seen in each topic. Zero if only the post was seen.
+    #[serde(default)]
+    topics: HashMap<PostId, CommentId>,
+    /// Time when each forum was marked as read.
+    #[serde(default)]
+    forums: HashMap<CommunityId, NaiveDateTime>,
+}
+
+impl PersonReadState {
+    fn new() -> Self {
+        PersonReadState {
+            since: Utc::now().naive_utc(),
+            topics: HashMap::new(),
+            forums: HashMap::new(),
+        }
+    }
+
+    /// Check if there is activity in the topic which wasn't seen yet. `comment_id` and `time` are
+    /// from the newest post or comment in the topic.
+    fn is_topic_unread(
+        &self,
+        community_id: CommunityId,
+        post_id: PostId,
+        comment_id: Option<CommentId>,
+        time: NaiveDateTime,
+    ) -> bool {
+        let marked_read = self
+            .forums
+            .get(&community_id)
+            .map(|f| *f.max(&self.since))
+            .unwrap_or(self.since);
+        if time <= marked_read {
+            return false;
+        }
+        match self.topics.get(&post_id) {
+            None => true,
+            Some(seen) => comment_id.map(|c| c.0 > seen.0).unwrap_or(false),
+        }
+    }
+}
+
+static READ_STATE: Lazy<RwLock<HashMap<PersonId, PersonReadState>>> =
+    Lazy::new(|| RwLock::new(persist::load(READ_STATE_FILE).unwrap_or_default()));
+
+/// Set when read state was changed and needs to be saved.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Write read state to disk in regular intervals, if it was changed.
+pub async fn persist_read_state() {
+    loop {
+        sleep(SAVE_INTERVAL).await;
+        if CHANGED.swap(false, Ordering::Relaxed) {
+            if let Err(e) = persist::save(READ_STATE_FILE, &*READ_STATE.read().unwrap()) {
+                error!("Failed to save {READ_STATE_FILE}: {e:#}");
+                CHANGED.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Change read state of the user. Tracking starts with the first change, everything before that
+/// counts as read.
+fn update<F: FnOnce(&mut PersonReadState)>(person_id: PersonId, f: F) {
+    let mut state = READ_STATE.write().unwrap();
+    f(state.entry(person_id).or_insert_with(PersonReadState::new));
+    CHANGED.store(true, Ordering::Relaxed);
+}
+
+/// Remember that user has seen the topic, up to the given comment.
+pub fn mark_topic_read(person_id: PersonId, post_id: PostId, comment_id: Option<CommentId>) {
+    let comment_id = comment_id.unwrap_or(CommentId(0));
+    update(person_id, |s| {
+        let seen = s.topics.entry(post_id).or_insert(comment_id);
+        if comment_id.0 > seen.0 {
+            *seen = comment_id;
+        }
+    });
+}
+
+/// Mark all current activity in the forum as read.
+pub fn mark_forum_read(person_id: PersonId, community_id: CommunityId) {
+    update(person_id, |s| {
+        s.forums.insert(community_id, Utc::now().naive_utc());
+    });
+}
+
+/// Highest comment id which the user has seen in the topic, or None if the topic was never
+/// opened.
+pub fn last_read_comment(person_id: PersonId, post_id: PostId) -> Option<CommentId> {
+    READ_STATE
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .and_then(|s| s.topics.get(&post_id).copied())
+}
+
+/// Time since when read state is tracked for the user, or None if it isn't tracked yet.
+pub fn read_tracking_since(person_id: PersonId) -> Option<NaiveDateTime> {
+    READ_STATE.read().unwrap().get(&person_id).map(|s| s.since)
+}
+
+/// Check if the topic has activity which the user hasn't seen yet. `last_reply` is the newest
+/// post or comment in the topic. Always false if read state isn't tracked for the user yet.
+pub fn is_topic_unread(
+    person_id: PersonId,
+    community_id: CommunityId,
+    last_reply: &PostOrComment,
+) -> bool {
+    READ_STATE
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .map(|s| {
+            s.is_topic_unread(
+                community_id,
+                last_reply.post_id,
+                last_reply.comment_id,
+                last_reply.time,
+            )
+        })
+        .unwrap_or(false)
+}
+
+#[test]
+fn topic_unread_state() {
+    let since = Utc::now().naive_utc();
+    let later = since + chrono::Duration::hours(1);
+    let (community, other_community) = (CommunityId(1), CommunityId(2));
+    let mut state = PersonReadState::new();
+    state.since = since;
+    // activity from before tracking started counts as read
+    assert!(!state.is_topic_unread(community, PostId(1), None, since));
+    // new topic which was never opened
+    assert!(state.is_topic_unread(community, PostId(1), None, later));
+    // opened topic only has unread comments after the last seen one
+    state.topics.insert(PostId(2), CommentId(10));
+    assert!(!state.is_topic_unread(community, PostId(2), Some(CommentId(10)), later));
+    assert!(state.is_topic_unread(community, PostId(2), Some(CommentId(11)), later));
+    // marking the forum read covers all older activity in it
+    state.forums.insert(community, later);
+    assert!(!state.is_topic_unread(community, PostId(1), None, later));
+    assert!(state.is_topic_unread(other_community, PostId(1), None, later));
+}
@@ -0,0 +1,308 @@
+use crate::{
+    api::{
+        categories::{
+            categories_error,
+            read_categories_config,
+            save_categories,
+            CategoryConfig,
+            CommunityConfig,
+        },
+        site::{
+            approve_registration_application,
+            edit_site,
+            list_registration_applications,
+            resolve_object,
+        },
+        user::{ban_user, get_banned_persons},
+    },
+    error::ErrorPage,
+    pagination::{PageLimit, Pagination},
+    site_fairing::SiteData,
+    utils::{empty_to_opt, is_admin, Context},
+};
+use anyhow::{anyhow, Error};
+use lemmy_api_common::{lemmy_db_schema::source::local_site::RegistrationMode, site::EditSite};
+use rocket::{form::Form, response::Redirect};
+use rocket_dyn_templates::{context, Template};
+use std::str::FromStr;
+
+fn ensure_admin(site_data: &SiteData) -> Result<(), Error> {
+    if is_admin(site_data) {
+        Ok(())
+    } else {
+        Err(anyhow!("not_an_admin"))
+    }
+}
+
+#[get("/acp/categories")]
+pub async fn edit_categories(site_data: SiteData) -> Result<Template, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let categories = read_categories_config()?;
+    let load_error = categories_error();
+    let ctx = Context::builder()
+        .title(format!(
+            "Forum categories - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { categories, load_error })
+        .build();
+    Ok(Template::render("admin/categories", ctx))
+}
+
+#[derive(FromForm)]
+pub struct CategoriesForm {
+    action: String,
+    category: Option<usize>,
+    community: Option<usize>,
+    title: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    collapsed: bool,
+    blurb: Option<String>,
+    query: Option<String>,
+}
+
+#[post("/acp/categories", data = "<form>")]
+pub async fn do_edit_categories(
+    form: Form<CategoriesForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let mut categories = read_categories_config()?;
+    let form = form.into_inner();
+    let title = form.title.and_then(empty_to_opt);
+    let description = form.description.and_then(empty_to_opt);
+    let icon = form.icon.and_then(empty_to_opt);
+    let blurb = form.blurb.and_then(empty_to_opt);
+
+    if form.action == "add_category" {
+        categories.push(CategoryConfig {
+            title: title.unwrap_or_default(),
+            description,
+            position: 0,
+            icon,
+            collapsed: form.collapsed,
+            communities: vec![],
+        });
+        save_categories(categories).await?;
+        return Ok(Redirect::to(uri!(edit_categories)));
+    }
+
+    let i = form
+        .category
+        .filter(|i| i < &categories.len())
+        .ok_or_else(|| anyhow!("Invalid category"))?;
+    match form.action.as_str() {
+        "edit_category" => {
+            let category = &mut categories[i];
+            category.title = title.unwrap_or_default();
+            category.description = description;
+            category.icon = icon;
+            category.collapsed = form.collapsed;
+        }
+        "remove_category" => {
+            categories.remove(i);
+        }
+        "category_up" => {
+            if i > 0 {
+                categories.swap(i, i - 1)
+            }
+        }
+        "category_down" => {
+            if i + 1 < categories.len() {
+                categories.swap(i, i + 1)
+            }
+        }
+        "add_community" => {
+            let query = form
+                .query
+                .and_then(empty_to_opt)
+                .ok_or_else(|| anyhow!("couldnt_find_community"))?;
+            let community = resolve_object(query, site_data.auth.clone())
+                .await?
+                .community
+                .ok_or_else(|| anyhow!("couldnt_find_community"))?;
+            categories[i].communities.push(CommunityConfig::Name(
+                community.community.actor_id.to_string(),
+            ));
+        }
+        action => {
+            let communities = &mut categories[i].communities;
+            let j = form
+                .community
+                .filter(|j| j < &communities.len())
+                .ok_or_else(|| anyhow!("Invalid community"))?;
+            match action {
+                "edit_community" => {
+                    let community = communities[j].name().to_string();
+                    communities[j] = if title.is_none() && blurb.is_none() {
+                        CommunityConfig::Name(community)
+                    } else {
+                        CommunityConfig::WithOverrides {
+                            community,
+                            title,
+                            blurb,
+                        }
+                    };
+                }
+                "remove_community" => {
+                    communities.remove(j);
+                }
+                "community_up" => {
+                    if j > 0 {
+                        communities.swap(j, j - 1)
+                    }
+                }
+                "community_down" => {
+                    if j + 1 < communities.len() {
+                        communities.swap(j, j + 1)
+                    }
+                }
+                _ => return Err(anyhow!("Invalid action").into()),
+            }
+        }
+    }
+    save_categories(categories).await?;
+    Ok(Redirect::to(uri!(edit_categories)))
+}
+
+#[get("/acp/banned")]
+pub async fn banned_users(site_data: SiteData) -> Result<Template, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let banned = get_banned_persons(site_data.auth.clone().unwrap())
+        .await?
+        .banned;
+    let ctx = Context::builder()
+        .title(format!(
+            "Banned users - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { banned })
+        .build();
+    Ok(Template::render("admin/banned", ctx))
+}
+
+#[derive(FromForm)]
+pub struct UnbanForm {
+    person_id: i32,
+}
+
+#[post("/acp/unban", data = "<form>")]
+pub async fn unban_user(form: Form<UnbanForm>, site_data: SiteData) -> Result<Redirect, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let auth = site_data.auth.clone().unwrap();
+    ban_user(form.person_id, false, None, false, None, auth).await?;
+    Ok(Redirect::to(uri!(banned_users)))
+}
+
+#[get("/acp/settings")]
+pub async fn site_settings(site_data: SiteData) -> Result<Template, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let ctx = Context::builder()
+        .title(format!(
+            "Site settings - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(())
+        .build();
+    Ok(Template::render("admin/settings", ctx))
+}
+
+#[derive(FromForm)]
+pub struct SiteSettingsForm {
+    name: String,
+    description: String,
+    sidebar: String,
+    legal_information: String,
+    registration_mode: String,
+    application_question: String,
+    require_email_verification: bool,
+    community_creation_admin_only: bool,
+    enable_downvotes: bool,
+    enable_nsfw: bool,
+    private_instance: bool,
+}
+
+#[post("/acp/settings", data = "<form>")]
+pub async fn do_site_settings(
+    form: Form<SiteSettingsForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let form = form.into_inner();
+    // empty strings clear the optional text fields in Lemmy
+    let params = EditSite {
+        name: Some(form.name),
+        description: Some(form.description),
+        sidebar: Some(form.sidebar),
+        legal_information: Some(form.legal_information),
+        registration_mode: Some(RegistrationMode::from_str(&form.registration_mode)?),
+        application_question: Some(form.application_question),
+        require_email_verification: Some(form.require_email_verification),
+        community_creation_admin_only: Some(form.community_creation_admin_only),
+        enable_downvotes: Some(form.enable_downvotes),
+        enable_nsfw: Some(form.enable_nsfw),
+        private_instance: Some(form.private_instance),
+        auth: site_data.auth.clone().unwrap(),
+        ..Default::default()
+    };
+    edit_site(params).await?;
+    Ok(Redirect::to(uri!(site_settings)))
+}
+
+#[get("/acp/applications?<page>&<all>")]
+pub async fn registration_applications(
+    page: Option<i32>,
+    all: Option<bool>,
+    site_data: SiteData,
+) -> Result<Template, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let page = page.unwrap_or(1);
+    let all = all.unwrap_or(false);
+    let applications = list_registration_applications(
+        page,
+        site_data.topics_per_page,
+        !all,
+        site_data.auth.clone().unwrap(),
+    )
+    .await?
+    .registration_applications;
+    let limit = PageLimit::Unknown(applications.len(), site_data.topics_per_page);
+    let pagination = Pagination::new(page, limit, format!("/acp/applications?all={all}&"));
+    let ctx = Context::builder()
+        .title(format!(
+            "Registration applications - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { applications, all, pagination })
+        .build();
+    Ok(Template::render("admin/applications", ctx))
+}
+
+#[derive(FromForm)]
+pub struct ApplicationForm {
+    id: i32,
+    approve: bool,
+    deny_reason: String,
+}
+
+#[post("/acp/applications", data = "<form>")]
+pub async fn handle_application(
+    form: Form<ApplicationForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    ensure_admin(&site_data)?;
+    let deny_reason = empty_to_opt(form.deny_reason.clone()).filter(|_| !form.approve);
+    approve_registration_application(
+        form.id,
+        form.approve,
+        deny_reason,
+        site_data.auth.clone().unwrap(),
+    )
+    .await?;
+    Ok(Redirect::to(uri!(registration_applications(_, _))))
+}
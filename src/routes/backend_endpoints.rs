@@ -1,11 +1,12 @@
 use crate::{
-    api::{comment::get_comment, community::get_community, user::get_person, NameOrId, CLIENT},
+    api::{community::get_community, user::get_person, NameOrId, CLIENT},
     env::lemmy_backend,
     error::ErrorPage,
     rocket_uri_macro_view_forum,
     rocket_uri_macro_view_profile,
     rocket_uri_macro_view_topic,
-    routes::auth,
+    routes::{auth, post::comment_link, threaded_view},
+    site_fairing::SiteData,
 };
 use anyhow::Error;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
@@ -36,7 +37,9 @@ pub async fn apub_community(
     Ok(Either::Left(Redirect::to(uri!(view_forum(
         f,
         Some(1),
-        Option::<String>::None
+        Option::<String>::None,
+        _,
+        _
     )))))
 }
 
@@ -57,21 +60,29 @@ pub async fn apub_post(id: i32, accept: AcceptHeader) -> ReturnType {
     if accept.0.starts_with("application/") {
         return foward_apub_fetch(format!("{}/post/{}", lemmy_backend(), id), accept).await;
     }
-    Ok(Either::Left(Redirect::to(uri!(view_topic(id, Some(1))))))
+    Ok(Either::Left(Redirect::to(uri!(view_topic(id, Some(1), _)))))
 }
 
 /// Fetch apub comment
 #[get("/comment/<t>")]
-pub async fn apub_comment(t: i32, accept: AcceptHeader, cookies: &CookieJar<'_>) -> ReturnType {
+pub async fn apub_comment(
+    t: i32,
+    accept: AcceptHeader,
+    site_data: SiteData,
+    cookies: &CookieJar<'_>,
+) -> ReturnType {
     if accept.0.starts_with("application/") {
         return foward_apub_fetch(format!("{}/comment/{}", lemmy_backend(), t), accept).await;
     }
-    let comment = get_comment(t, auth(cookies)).await?;
-    // TODO: figure out actual page
-    Ok(Either::Left(Redirect::to(format!(
-        "/view_topic?t={}&page=1#p{}",
-        comment.comment_view.post.id, t
-    ))))
+    Ok(Either::Left(Redirect::to(
+        comment_link(
+            t,
+            threaded_view(cookies),
+            site_data.posts_per_page,
+            site_data.auth,
+        )
+        .await?,
+    )))
 }
 
 async fn foward_apub_fetch(url: String, accept: AcceptHeader) -> ReturnType {
@@ -1,25 +1,39 @@
 use crate::{
     api::{
-        comment::{create_comment, edit_comment, get_comment, list_comments},
+        comment::{create_comment, edit_comment, get_comment, list_comments_page},
+        last_reply::record_comment,
         post::get_post,
     },
     error::ErrorPage,
-    pagination::PAGE_ITEMS,
+    read_state::mark_topic_read,
     rocket_uri_macro_login,
-    routes::post::rocket_uri_macro_view_topic,
+    routes::{
+        post::{comment_link, topic_comments},
+        threaded_view,
+    },
     site_fairing::SiteData,
     utils::{replace_smilies, Context},
 };
-use lemmy_api_common::lemmy_db_views::structs::CommentView;
-use rocket::{form::Form, response::Redirect, Either};
+use anyhow::{anyhow, Error};
+use futures::future::try_join_all;
+use itertools::Itertools;
+use lemmy_api_common::sensitive::Sensitive;
+use rocket::{
+    form::Form,
+    http::{Cookie, CookieJar, SameSite},
+    response::Redirect,
+    Either,
+};
 use rocket_dyn_templates::{context, Template};
 
-#[get("/comment_editor?<t>&<edit>&<reply>")]
+#[get("/comment_editor?<t>&<edit>&<reply>&<quote>")]
 pub async fn comment_editor(
     t: i32,
     edit: Option<i32>,
     reply: Option<i32>,
+    quote: Vec<String>,
     site_data: SiteData,
+    cookies: &CookieJar<'_>,
 ) -> Result<Either<Template, Redirect>, ErrorPage> {
     if site_data.auth.is_none() {
         return Ok(Either::Right(Redirect::to(uri!(login))));
@@ -38,9 +52,110 @@ pub async fn comment_editor(
                 .await?,
             ))
         }
-        None => Ok(Either::Left(
-            render_editor(t, None, None, reply, site_data).await?,
-        )),
+        None => {
+            let post = get_post(t, site_data.auth.clone()).await?.post_view.post;
+            if post.locked {
+                return Ok(Either::Left(render_topic_locked(t, site_data)));
+            }
+            let message = if quote.is_empty() {
+                None
+            } else {
+                // quotes were used, so clear multi-quote selection
+                cookies.remove(Cookie::named(MULTIQUOTE_COOKIE));
+                Some(quote_items(&quote, site_data.auth.clone()).await?)
+            };
+            Ok(Either::Left(
+                render_editor(t, message, None, reply, site_data).await?,
+            ))
+        }
+    }
+}
+
+/// Shown instead of the editor when trying to reply to a locked topic
+fn render_topic_locked(t: i32, site_data: SiteData) -> Template {
+    let message = "This topic is locked, you cannot make further replies.";
+    let link_text = "Return to the topic";
+    let link_url = format!("/view_topic?t={t}");
+    let ctx = Context::builder()
+        .title(message)
+        .site_data(site_data)
+        .other(context! { message, link_text, link_url })
+        .build();
+    Template::render("message", ctx)
+}
+
+/// Build markdown blockquotes for the given items, which are written as `p<id>` for posts and
+/// `c<id>` for comments.
+async fn quote_items(items: &[String], auth: Option<Sensitive<String>>) -> Result<String, Error> {
+    let quotes = try_join_all(items.iter().map(|i| quote_item(i, auth.clone()))).await?;
+    Ok(quotes.concat())
+}
+
+async fn quote_item(item: &str, auth: Option<Sensitive<String>>) -> Result<String, Error> {
+    let (author, link, content) = if let Some(id) = item.strip_prefix('p') {
+        let p = get_post(id.parse()?, auth).await?.post_view;
+        (
+            p.creator.name,
+            p.post.ap_id,
+            p.post.body.unwrap_or_default(),
+        )
+    } else if let Some(id) = item.strip_prefix('c') {
+        let c = get_comment(id.parse()?, auth).await?.comment_view;
+        (c.creator.name, c.comment.ap_id, c.comment.content)
+    } else {
+        return Err(anyhow!("Invalid quote {item}"));
+    };
+    let quoted = content.lines().map(|l| format!("> {l}")).join("\n");
+    Ok(format!("> **{author}** [wrote]({link}):\n>\n{quoted}\n\n"))
+}
+
+/// Name of cookie which holds posts and comments selected for multi-quote, in the format
+/// `<topic id>:<item>,<item>`. Only items from a single topic can be selected.
+static MULTIQUOTE_COOKIE: &str = "multiquote";
+
+/// Returns the items from multi-quote selection which belong to the given topic.
+pub fn multiquote_items(cookies: &CookieJar<'_>, t: i32) -> Vec<String> {
+    cookies
+        .get(MULTIQUOTE_COOKIE)
+        .and_then(|c| {
+            let (topic, items) = c.value().split_once(':')?;
+            (topic.parse::<i32>().ok() == Some(t))
+                .then(|| items.split(',').map(str::to_string).collect())
+        })
+        .unwrap_or_default()
+}
+
+#[derive(FromForm)]
+pub struct MultiquoteForm {
+    t: i32,
+    /// Post or comment id with prefix, eg `p12` or `c34`
+    item: String,
+}
+
+/// Add or remove a post or comment from multi-quote selection
+#[post("/multiquote", data = "<form>")]
+pub async fn multiquote(form: Form<MultiquoteForm>, cookies: &CookieJar<'_>) -> Redirect {
+    let MultiquoteForm { t, item } = form.into_inner();
+    let mut items = multiquote_items(cookies, t);
+    if let Some(i) = items.iter().position(|i| i == &item) {
+        items.remove(i);
+    } else {
+        items.push(item.clone());
+    }
+    if items.is_empty() {
+        cookies.remove(Cookie::named(MULTIQUOTE_COOKIE));
+    } else {
+        let value = format!("{t}:{}", items.join(","));
+        cookies.add(
+            Cookie::build(MULTIQUOTE_COOKIE, value)
+                .same_site(SameSite::Strict)
+                .finish(),
+        );
+    }
+    // go back to the post or comment
+    match item.strip_prefix('c') {
+        Some(c) => Redirect::to(format!("/viewtopic?p={c}")),
+        None => Redirect::to(format!("/view_topic?t={t}")),
     }
 }
 
@@ -62,13 +177,15 @@ async fn render_editor(
     let message = message.unwrap_or_default();
 
     // for topic review
-    let all_comments = list_comments(post.post_view.post.id, site_data.auth.clone()).await?;
-    let page_comments: Vec<CommentView> = all_comments
-        .iter()
-        .rev()
-        .take(PAGE_ITEMS as usize)
-        .cloned()
-        .collect();
+    let comments = list_comments_page(
+        post.post_view.post.id,
+        1,
+        site_data.posts_per_page,
+        true,
+        site_data.auth.clone(),
+    )
+    .await?;
+    let page_comments = topic_comments(&comments, &[]);
 
     let ctx = Context::builder()
         .title(format!(
@@ -76,7 +193,7 @@ async fn render_editor(
             site_data.site.site_view.site.name
         ))
         .site_data(site_data)
-        .other(context! { post, page_comments, message, editor_action, all_comments })
+        .other(context! { post, page_comments, message, editor_action })
         .build();
     Ok(Template::render("comment_editor", ctx))
 }
@@ -98,6 +215,7 @@ pub async fn do_comment(
     reply: Option<i32>,
     form: Form<CommentForm>,
     site_data: SiteData,
+    cookies: &CookieJar<'_>,
 ) -> Result<Either<Template, Redirect>, ErrorPage> {
     let message = replace_smilies(&form.message, &site_data);
     if form.preview.is_some() {
@@ -106,10 +224,36 @@ pub async fn do_comment(
         ));
     }
 
-    let auth = site_data.auth.expect("user not logged in");
-    match edit {
-        Some(e) => edit_comment(e, message, auth).await?,
-        None => create_comment(t, message, reply, auth).await?,
+    let auth = site_data.auth.clone().expect("user not logged in");
+    let comment_id = match edit {
+        Some(e) => {
+            edit_comment(e, message, auth.clone()).await?;
+            e
+        }
+        None => {
+            let post = get_post(t, Some(auth.clone())).await?.post_view.post;
+            if post.locked {
+                return Ok(Either::Left(render_topic_locked(t, site_data)));
+            }
+            let comment = create_comment(t, message, reply, auth.clone())
+                .await?
+                .comment_view;
+            record_comment(&comment);
+            mark_topic_read(
+                comment.creator.id,
+                comment.post.id,
+                Some(comment.comment.id),
+            );
+            comment.comment.id.0
+        }
     };
-    Ok(Either::Right(Redirect::to(uri!(view_topic(t, Some(1))))))
+    // go to the page where the new or edited comment is shown
+    let link = comment_link(
+        comment_id,
+        threaded_view(cookies),
+        site_data.posts_per_page,
+        Some(auth),
+    )
+    .await?;
+    Ok(Either::Right(Redirect::to(link)))
 }
@@ -1,28 +1,153 @@
 use crate::{
     api::{
         comment::report_comment,
-        community::{follow_community, get_community},
-        extra::{get_last_reply_in_thread, PostOrComment},
-        post::{list_posts, report_post},
+        community::{
+            create_community,
+            delete_community,
+            edit_community,
+            follow_community,
+            get_community,
+            hide_community,
+        },
+        image::upload_image,
+        last_reply::{get_last_replies_in_threads, PostOrComment},
+        post::{list_featured_posts, list_posts, report_post},
         NameOrId,
     },
-    env::increased_rate_limit,
-    pagination::{PageLimit, Pagination, PAGE_ITEMS},
+    forum_sort::{ForumSort, SORT_OPTIONS},
+    pagination::{PageLimit, Pagination, MAX_PAGE_ITEMS},
+    read_state::{is_topic_unread, mark_forum_read},
     routes::ErrorPage,
     site_fairing::SiteData,
     template_helpers::i18n_,
-    utils::Context,
+    user_prefs::{get_user_prefs, save_user_prefs, UserPrefs},
+    utils::{can_create_community, can_moderate, empty_to_opt, Context},
 };
-use anyhow::Error;
-use futures::future::join_all;
-use rocket::form::Form;
+use anyhow::{anyhow, Error};
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::future::join3;
+use lemmy_api_common::{
+    community::{CreateCommunity, EditCommunity, GetCommunityResponse},
+    lemmy_db_schema::newtypes::CommunityId,
+    lemmy_db_views::structs::PostView,
+    sensitive::Sensitive,
+};
+use rocket::{form::Form, fs::TempFile, response::Redirect};
 use rocket_dyn_templates::{context, Template};
+use serde::Serialize;
+
+/// Topic in a forum listing, with the data needed to render its row
+#[derive(Serialize)]
+struct ForumTopic {
+    #[serde(flatten)]
+    post_view: PostView,
+    last_reply: PostOrComment,
+    unread: bool,
+}
+
+async fn forum_topics(
+    posts: Vec<PostView>,
+    site_data: &SiteData,
+) -> Result<Vec<ForumTopic>, Error> {
+    let last_replies = get_last_replies_in_threads(&posts, site_data.auth.clone()).await?;
+    let person_id = site_data
+        .site
+        .my_user
+        .as_ref()
+        .map(|u| u.local_user_view.person.id);
+    Ok(posts
+        .into_iter()
+        .zip(last_replies.into_iter())
+        .map(|(post_view, last_reply)| {
+            let unread = person_id
+                .map(|p| is_topic_unread(p, post_view.community.id, &last_reply))
+                .unwrap_or(false);
+            ForumTopic {
+                post_view,
+                last_reply,
+                unread,
+            }
+        })
+        .collect())
+}
+
+/// Highest number of pages which are fetched from the backend to find topics for the age filter
+static MAX_FILTER_PAGES: i32 = 10;
+
+/// Options for showing only topics with activity in the previous number of days. Zero means all
+/// topics.
+static AGE_OPTIONS: [(i64, &str); 8] = [
+    (0, "All topics"),
+    (1, "1 day"),
+    (7, "7 days"),
+    (14, "2 weeks"),
+    (30, "1 month"),
+    (90, "3 months"),
+    (180, "6 months"),
+    (365, "1 year"),
+];
+
+fn is_featured(post: &PostView) -> bool {
+    post.post.featured_community || post.post.featured_local
+}
 
-#[get("/view_forum?<f>&<page>&<action>")]
+/// Page of topics with activity after `cutoff`. Lemmy can't filter by age, so topics are fetched
+/// in large pages and filtered until the requested page is full, or `MAX_FILTER_PAGES` were
+/// fetched. When sorting by last reply, all topics after the first old one are also old, so
+/// fetching stops there.
+async fn list_recent_posts(
+    f: i32,
+    page: i32,
+    page_size: i32,
+    sort: ForumSort,
+    cutoff: NaiveDateTime,
+    auth: Option<Sensitive<String>>,
+) -> Result<(Vec<PostView>, PageLimit), Error> {
+    let skip = ((page - 1) * page_size) as usize;
+    // one more than needed, to know if there is a next page
+    let needed = skip + page_size as usize + 1;
+    let mut matching = vec![];
+    for backend_page in 1..=MAX_FILTER_PAGES {
+        let posts = list_posts(
+            f,
+            MAX_PAGE_ITEMS,
+            backend_page,
+            sort.sort_type(),
+            auth.clone(),
+        )
+        .await?
+        .posts;
+        let is_last_page = (posts.len() as i32) < MAX_PAGE_ITEMS;
+        let has_old = posts.iter().any(|p| p.counts.newest_comment_time < cutoff);
+        matching.extend(
+            posts
+                .into_iter()
+                .filter(|p| p.counts.newest_comment_time >= cutoff && !is_featured(p)),
+        );
+        if is_last_page || matching.len() >= needed || (sort == ForumSort::LastReply && has_old) {
+            break;
+        }
+    }
+    let has_next_page = matching.len() >= needed;
+    let posts: Vec<PostView> = matching
+        .into_iter()
+        .skip(skip)
+        .take(page_size as usize)
+        .collect();
+    let limit = match has_next_page {
+        true => PageLimit::Unknown(page_size as usize, page_size),
+        false => PageLimit::Known(page),
+    };
+    Ok((posts, limit))
+}
+
+#[get("/view_forum?<f>&<page>&<action>&<sort>&<days>")]
 pub async fn view_forum(
     f: i32,
     page: Option<i32>,
     action: Option<String>,
+    sort: Option<ForumSort>,
+    days: Option<i64>,
     site_data: SiteData,
 ) -> Result<Template, ErrorPage> {
     let auth = site_data.auth.clone();
@@ -31,33 +156,106 @@ pub async fn view_forum(
             follow_community(f, true, auth.clone().unwrap()).await?;
         } else if action == "unsubscribe" {
             follow_community(f, false, auth.clone().unwrap()).await?;
+        } else if action == "mark_read" {
+            if let Some(my_user) = &site_data.site.my_user {
+                mark_forum_read(my_user.local_user_view.person.id, CommunityId(f));
+            }
         }
     }
+
+    // use sort options from params, or otherwise the ones which the user selected previously
+    let person_id = site_data
+        .site
+        .my_user
+        .as_ref()
+        .map(|u| u.local_user_view.person.id);
+    let prefs = person_id.map(get_user_prefs).unwrap_or_default();
+    let changed = (sort.is_some() && sort != prefs.forum_sort)
+        || (days.is_some() && days != prefs.forum_days);
+    let sort = sort.or(prefs.forum_sort).unwrap_or_default();
+    let days = days
+        .or(prefs.forum_days)
+        .filter(|d| AGE_OPTIONS.iter().any(|(o, _)| o == d))
+        .unwrap_or(0);
+    if let (Some(person_id), true) = (person_id, changed) {
+        let prefs = UserPrefs {
+            forum_sort: Some(sort),
+            forum_days: Some(days),
+            ..prefs
+        };
+        save_user_prefs(person_id, prefs)?;
+    }
+
     let page = page.unwrap_or(1);
-    let posts = list_posts(f, PAGE_ITEMS, page, auth.clone()).await?.posts;
-    let community = get_community(NameOrId::Id(f), auth.clone()).await?;
-    let last_replies = if increased_rate_limit() {
-        join_all(
-            posts
-                .iter()
-                .map(|p| get_last_reply_in_thread(p, auth.clone())),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<PostOrComment>, Error>>()?
-    } else {
-        vec![]
+    let posts = async {
+        match days {
+            0 => list_posts(
+                f,
+                site_data.topics_per_page,
+                page,
+                sort.sort_type(),
+                auth.clone(),
+            )
+            .await
+            .map(|res| {
+                let limit = PageLimit::Unknown(res.posts.len(), site_data.topics_per_page);
+                (res.posts, limit)
+            }),
+            _ => {
+                let cutoff = Utc::now().naive_utc() - Duration::days(days);
+                list_recent_posts(
+                    f,
+                    page,
+                    site_data.topics_per_page,
+                    sort,
+                    cutoff,
+                    auth.clone(),
+                )
+                .await
+            }
+        }
     };
+    let (posts, stickies, announcements) = join3(
+        posts,
+        list_featured_posts(Some(CommunityId(f)), auth.clone()),
+        list_featured_posts(None, auth.clone()),
+    )
+    .await;
+    let (mut posts, limit) = posts?;
+    // featured posts are shown in separate sections on every page, so dont repeat them
+    posts.retain(|p| !is_featured(p));
+    let mut stickies = stickies?;
+    stickies.retain(|p| !p.post.featured_local);
+    let community = get_community(NameOrId::Id(f), auth.clone()).await?;
+
+    let topics = forum_topics(posts, &site_data).await?;
+    let stickies = forum_topics(stickies, &site_data).await?;
+    let announcements = forum_topics(announcements?, &site_data).await?;
 
-    let limit = PageLimit::Unknown(posts.len());
-    let pagination = Pagination::new(page, limit, format!("/view_forum?f={f}&"));
+    let pagination = Pagination::new(
+        page,
+        limit,
+        format!("/view_forum?f={f}&sort={}&days={days}&", sort.name()),
+    );
+    let sort_options = SORT_OPTIONS.to_vec();
+    let age_options = AGE_OPTIONS.to_vec();
     let ctx = Context::builder()
         .title(format!(
             "{} - {}",
             community.community_view.community.title, site_data.site.site_view.site.name
         ))
         .site_data(site_data)
-        .other(context! { community, posts, last_replies, pagination })
+        .other(context! {
+            community,
+            announcements,
+            stickies,
+            topics,
+            pagination,
+            sort,
+            days,
+            sort_options,
+            age_options
+        })
         .build();
     Ok(Template::render("view_forum", ctx))
 }
@@ -114,3 +312,177 @@ pub async fn do_report(
         .build();
     Ok(Template::render("message", ctx))
 }
+
+#[get("/create_forum")]
+pub async fn create_forum(site_data: SiteData) -> Result<Template, ErrorPage> {
+    if !can_create_community(&site_data) {
+        return Err(anyhow!("only_admins_can_create_communities").into());
+    }
+    let ctx = Context::builder()
+        .title(format!(
+            "Create forum - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(())
+        .build();
+    Ok(Template::render("forum_editor", ctx))
+}
+
+/// Returns an error unless the user is moderator of the forum or admin
+async fn ensure_forum_moderator(
+    f: i32,
+    site_data: &SiteData,
+) -> Result<GetCommunityResponse, Error> {
+    let community = get_community(NameOrId::Id(f), site_data.auth.clone()).await?;
+    if !can_moderate(site_data, &community.moderators) {
+        return Err(anyhow!("not_a_moderator"));
+    }
+    Ok(community)
+}
+
+#[get("/forum_settings?<f>")]
+pub async fn forum_settings(f: i32, site_data: SiteData) -> Result<Template, ErrorPage> {
+    let community = ensure_forum_moderator(f, &site_data).await?;
+    let ctx = Context::builder()
+        .title(format!(
+            "Forum settings - {}",
+            community.community_view.community.title
+        ))
+        .site_data(site_data)
+        .other(context! { community })
+        .build();
+    Ok(Template::render("forum_editor", ctx))
+}
+
+#[derive(FromForm)]
+pub struct ForumEditorForm<'r> {
+    /// Only used when creating a new forum, the name can't be changed later
+    name: Option<String>,
+    title: String,
+    description: String,
+    icon_delete: bool,
+    icon_upload_file: TempFile<'r>,
+    banner_delete: bool,
+    banner_upload_file: TempFile<'r>,
+    nsfw: bool,
+    posting_restricted_to_mods: bool,
+}
+
+/// Returns the url of the uploaded image, or Some("") if the existing image should be deleted
+async fn forum_image(
+    file: &mut TempFile<'_>,
+    delete: bool,
+    auth: Sensitive<String>,
+    site_data: &SiteData,
+) -> Result<Option<String>, Error> {
+    if file.len() != 0 {
+        Ok(Some(upload_image(file, auth, site_data).await?.to_string()))
+    } else if delete {
+        Ok(Some("".to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+#[post("/create_forum", data = "<form>")]
+pub async fn do_create_forum(
+    form: Form<ForumEditorForm<'_>>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    if !can_create_community(&site_data) {
+        return Err(anyhow!("only_admins_can_create_communities").into());
+    }
+    let auth = site_data.auth.clone().unwrap();
+    let mut form = form.into_inner();
+    let name = form
+        .name
+        .and_then(empty_to_opt)
+        .ok_or_else(|| anyhow!("Forum name is required"))?;
+    let icon = forum_image(&mut form.icon_upload_file, false, auth.clone(), &site_data).await?;
+    let banner = forum_image(
+        &mut form.banner_upload_file,
+        false,
+        auth.clone(),
+        &site_data,
+    )
+    .await?;
+    let params = CreateCommunity {
+        name,
+        title: form.title,
+        description: empty_to_opt(form.description),
+        icon,
+        banner,
+        nsfw: Some(form.nsfw),
+        posting_restricted_to_mods: Some(form.posting_restricted_to_mods),
+        auth,
+        ..Default::default()
+    };
+    let community = create_community(params).await?.community_view.community;
+    Ok(Redirect::to(uri!(view_forum(community.id.0, _, _, _, _))))
+}
+
+#[post("/forum_settings?<f>", data = "<form>")]
+pub async fn do_forum_settings(
+    f: i32,
+    form: Form<ForumEditorForm<'_>>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    ensure_forum_moderator(f, &site_data).await?;
+    let auth = site_data.auth.clone().unwrap();
+    let mut form = form.into_inner();
+    let icon = forum_image(
+        &mut form.icon_upload_file,
+        form.icon_delete,
+        auth.clone(),
+        &site_data,
+    )
+    .await?;
+    let banner = forum_image(
+        &mut form.banner_upload_file,
+        form.banner_delete,
+        auth.clone(),
+        &site_data,
+    )
+    .await?;
+    let params = EditCommunity {
+        community_id: CommunityId(f),
+        title: Some(form.title),
+        description: Some(form.description),
+        icon,
+        banner,
+        nsfw: Some(form.nsfw),
+        posting_restricted_to_mods: Some(form.posting_restricted_to_mods),
+        auth,
+        ..Default::default()
+    };
+    edit_community(params).await?;
+    Ok(Redirect::to(uri!(forum_settings(f))))
+}
+
+#[derive(FromForm)]
+pub struct ForumActionForm {
+    action: String,
+    reason: String,
+}
+
+/// Delete or hide a forum, or undo that
+#[post("/forum_action?<f>", data = "<form>")]
+pub async fn forum_action(
+    f: i32,
+    form: Form<ForumActionForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    ensure_forum_moderator(f, &site_data).await?;
+    let auth = site_data.auth.clone().unwrap();
+    let community_id = CommunityId(f);
+    let reason = empty_to_opt(form.reason.clone());
+    match form.action.as_str() {
+        "delete" => delete_community(community_id, true, auth).await?,
+        "restore" => delete_community(community_id, false, auth).await?,
+        "hide" => hide_community(community_id, true, reason, auth).await?,
+        "unhide" => hide_community(community_id, false, reason, auth).await?,
+        _ => return Err(anyhow!("Invalid action {}", form.action).into()),
+    };
+    Ok(Redirect::to(uri!(forum_settings(f))))
+}
@@ -1,3 +1,4 @@
+pub mod admin;
 pub mod backend_endpoints;
 pub mod comment;
 pub mod community;
@@ -24,3 +25,19 @@ pub fn build_jwt_cookie(jwt: Sensitive<String>) -> Cookie<'static> {
         .same_site(SameSite::Strict)
         .finish()
 }
+
+/// Returns true if the user chose to show replies in topics as a tree.
+pub fn threaded_view(cookies: &CookieJar<'_>) -> bool {
+    cookies
+        .get("topic_view")
+        .map(|c| c.value() == "threaded")
+        .unwrap_or(false)
+}
+
+pub fn build_topic_view_cookie(threaded: bool) -> Cookie<'static> {
+    let value = if threaded { "threaded" } else { "flat" };
+    Cookie::build("topic_view", value)
+        .same_site(SameSite::Strict)
+        .permanent()
+        .finish()
+}
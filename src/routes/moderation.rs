@@ -1,22 +1,48 @@
 use crate::{
     api::{
-        comment::get_comment,
-        moderation::{get_mod_log, remove_comment, remove_post},
-        post::get_post,
+        comment::{
+            create_comment,
+            delete_comment,
+            get_comment,
+            list_comment_reports,
+            resolve_comment_report,
+        },
+        community::{add_mod_to_community, ban_from_community, get_community, transfer_community},
+        last_reply::{invalidate_last_reply, record_comment},
+        moderation::{feature_post, get_mod_log, lock_post, remove_comment, remove_post},
+        post::{get_post, list_post_reports, resolve_post_report},
+        user::get_person,
+        NameOrId,
     },
     error::ErrorPage,
+    pagination::{PageLimit, Pagination, MAX_PAGE_ITEMS},
+    rocket_uri_macro_login,
+    rocket_uri_macro_view_topic,
     site_fairing::SiteData,
     template_helpers::i18n_,
-    utils::Context,
+    utils::{can_moderate, Context},
 };
-use anyhow::anyhow;
-use chrono::NaiveDateTime;
+use anyhow::{anyhow, Error};
+use chrono::{NaiveDateTime, Utc};
 use comrak::{markdown_to_html, ComrakOptions};
+use futures::future::join;
 use itertools::Itertools;
-use lemmy_api_common::lemmy_db_schema::source::community::CommunitySafe;
+use lemmy_api_common::{
+    lemmy_db_schema::{
+        newtypes::{CommunityId, PersonId},
+        source::{community::CommunitySafe, person::PersonSafe, post::Post},
+        ModlogActionType,
+        PostFeatureType,
+    },
+    lemmy_db_views::structs::{CommentReportView, PostReportView},
+    lemmy_db_views_moderator::structs::ModBanFromCommunityView,
+    sensitive::Sensitive,
+    site::GetModlog,
+};
 use rocket::{form::Form, response::Redirect, Either};
 use rocket_dyn_templates::{context, Template};
 use serde::Serialize;
+use std::{collections::HashSet, str::FromStr};
 
 #[get("/remove_item?<t>&<r>")]
 pub async fn remove_item(
@@ -24,13 +50,33 @@ pub async fn remove_item(
     r: Option<i32>,
     site_data: SiteData,
 ) -> Result<Template, ErrorPage> {
-    if t.is_some() == r.is_some() {
-        return Err(anyhow!("One of params t and r needs to be set").into());
-    }
+    // items which are already removed can be restored with the same form
+    let removed = match (t, r) {
+        (Some(t), None) => {
+            get_post(t, site_data.auth.clone())
+                .await?
+                .post_view
+                .post
+                .removed
+        }
+        (None, Some(r)) => {
+            get_comment(r, site_data.auth.clone())
+                .await?
+                .comment_view
+                .comment
+                .removed
+        }
+        _ => return Err(anyhow!("One of params t and r needs to be set").into()),
+    };
+    let title = if removed {
+        "Restore item"
+    } else {
+        "Delete item"
+    };
     let ctx = Context::builder()
-        .title("Delete item")
+        .title(title)
         .site_data(site_data)
-        .other(context! { t, r })
+        .other(context! { t, r, removed })
         .build();
     Ok(Template::render("remove_item", ctx))
 }
@@ -40,6 +86,8 @@ pub struct RemoveItemForm {
     t: Option<i32>,
     r: Option<i32>,
     delete_reason: String,
+    /// True to restore an item which was removed before
+    restore: bool,
     cancel: Option<String>,
 }
 
@@ -72,14 +120,24 @@ pub async fn do_remove_item<'r>(
     }
     match (form.t, form.r) {
         (Some(t), None) => {
-            remove_post(t, form.delete_reason.clone(), auth).await?;
+            let post = remove_post(t, !form.restore, form.delete_reason.clone(), auth)
+                .await?
+                .post_view;
+            invalidate_last_reply(post.post.id, post.community.id);
         }
         (None, Some(r)) => {
-            remove_comment(r, form.delete_reason.clone(), auth).await?;
+            let comment = remove_comment(r, !form.restore, form.delete_reason.clone(), auth)
+                .await?
+                .comment_view;
+            invalidate_last_reply(comment.post.id, comment.community.id);
         }
         _ => return Err(anyhow!("Invalid parameters").into()),
     };
-    let message = "Item deleted successfully";
+    let message = if form.restore {
+        "Item restored successfully"
+    } else {
+        "Item deleted successfully"
+    };
     let link_text = "Click here to return";
     let ctx = Context::builder()
         .title(message)
@@ -89,27 +147,222 @@ pub async fn do_remove_item<'r>(
     Ok(Either::Left(Template::render("message", ctx)))
 }
 
-#[get("/mod_log")]
-pub async fn mod_log(site_data: SiteData) -> Result<Template, ErrorPage> {
-    let mod_log = get_mod_log(site_data.auth.clone()).await?;
+#[get("/lock_topic?<t>")]
+pub async fn lock_topic(t: i32, site_data: SiteData) -> Result<Template, ErrorPage> {
+    let post = get_post(t, site_data.auth.clone()).await?;
+    if !can_moderate(&site_data, &post.moderators) {
+        return Err(anyhow!("not_a_moderator").into());
+    }
+    let post = post.post_view;
+    let title = if post.post.locked {
+        "Unlock topic"
+    } else {
+        "Lock topic"
+    };
+    let ctx = Context::builder()
+        .title(title)
+        .site_data(site_data)
+        .other(context! { post })
+        .build();
+    Ok(Template::render("lock_topic", ctx))
+}
+
+#[derive(FromForm)]
+pub struct LockTopicForm {
+    t: i32,
+    lock: bool,
+    reason: String,
+    cancel: Option<String>,
+}
+
+#[post("/do_lock_topic", data = "<form>")]
+pub async fn do_lock_topic(
+    form: Form<LockTopicForm>,
+    site_data: SiteData,
+) -> Result<Either<Template, Redirect>, ErrorPage> {
+    if form.cancel.is_some() {
+        return Ok(Either::Right(Redirect::to(uri!(view_topic(
+            form.t,
+            Some(1),
+            _
+        )))));
+    }
+    let auth = site_data.auth.clone().unwrap();
+    let post = get_post(form.t, Some(auth.clone())).await?;
+    if !can_moderate(&site_data, &post.moderators) {
+        return Err(anyhow!("not_a_moderator").into());
+    }
+    // Lemmy doesn't store a reason for locking, so it is posted as a reply. This needs to happen
+    // while the topic is unlocked.
+    let reason_comment = |action: &str| {
+        let reason = form.reason.trim();
+        (!reason.is_empty()).then(|| format!("**{action}:** {reason}"))
+    };
+    if form.lock {
+        let comment = match reason_comment("Topic locked") {
+            Some(content) => Some(create_comment(form.t, content, None, auth.clone()).await?),
+            None => None,
+        };
+        if let Err(e) = lock_post(form.t, true, auth.clone()).await {
+            // dont leave the reason behind if the topic couldn't be locked
+            if let Some(comment) = comment {
+                let comment_id = comment.comment_view.comment.id.0;
+                if let Err(e) = delete_comment(comment_id, true, auth).await {
+                    warn!("Failed to delete lock reason {comment_id}: {e:#}");
+                }
+            }
+            return Err(e.into());
+        }
+        if let Some(comment) = comment {
+            record_comment(&comment.comment_view);
+        }
+    } else {
+        lock_post(form.t, false, auth.clone()).await?;
+        if let Some(content) = reason_comment("Topic unlocked") {
+            let comment = create_comment(form.t, content, None, auth).await?;
+            record_comment(&comment.comment_view);
+        }
+    }
+    let message = if form.lock {
+        "Topic locked successfully"
+    } else {
+        "Topic unlocked successfully"
+    };
+    let link_text = "Click here to return";
+    let link_url = format!("/view_topic?t={}", form.t);
+    let ctx = Context::builder()
+        .title(message)
+        .site_data(site_data)
+        .other(context! { message, link_text, link_url })
+        .build();
+    Ok(Either::Left(Template::render("message", ctx)))
+}
+
+#[derive(FromForm)]
+pub struct FeatureTopicForm {
+    t: i32,
+    action: String,
+}
+
+/// Make topic sticky in its forum, or announce it on the whole site
+#[post("/feature_topic", data = "<form>")]
+pub async fn feature_topic(
+    form: Form<FeatureTopicForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    let (featured, feature_type) = match form.action.as_str() {
+        "sticky" => (true, PostFeatureType::Community),
+        "unsticky" => (false, PostFeatureType::Community),
+        "announce" => (true, PostFeatureType::Local),
+        "unannounce" => (false, PostFeatureType::Local),
+        _ => return Err(anyhow!("Invalid action {}", form.action).into()),
+    };
+    feature_post(form.t, featured, feature_type, auth).await?;
+    Ok(Redirect::to(uri!(view_topic(form.t, Some(1), _))))
+}
+
+/// Action types which can be selected in the mod log filter, with their labels
+static MOD_LOG_ACTIONS: [(&str, &str); 16] = [
+    ("All", "All actions"),
+    ("ModRemovePost", "Remove post"),
+    ("ModLockPost", "Lock post"),
+    ("ModFeaturePost", "Sticky or announce post"),
+    ("ModRemoveComment", "Remove comment"),
+    ("ModRemoveCommunity", "Remove forum"),
+    ("ModBanFromCommunity", "Ban from forum"),
+    ("ModAddCommunity", "Appoint forum moderator"),
+    ("ModTransferCommunity", "Transfer forum"),
+    ("ModAdd", "Appoint admin"),
+    ("ModBan", "Ban from site"),
+    ("ModHideCommunity", "Hide forum"),
+    ("AdminPurgePerson", "Purge user"),
+    ("AdminPurgeCommunity", "Purge forum"),
+    ("AdminPurgePost", "Purge post"),
+    ("AdminPurgeComment", "Purge comment"),
+];
+
+/// Mod log with optional filters. Without an action filter Lemmy paginates each category of
+/// actions separately, so a page shows up to one page size of entries per category, and the
+/// order of entries is only exact within a page. Filtering by action gives exact pagination.
+#[get("/mod_log?<f>&<m>&<action>&<page>")]
+pub async fn mod_log(
+    f: Option<i32>,
+    m: Option<i32>,
+    action: Option<String>,
+    page: Option<i32>,
+    site_data: SiteData,
+) -> Result<Template, ErrorPage> {
+    let page = page.unwrap_or(1);
+    let action = action.unwrap_or_else(|| "All".to_string());
+    let params = GetModlog {
+        community_id: f.map(CommunityId),
+        mod_person_id: m.map(PersonId),
+        type_: Some(ModlogActionType::from_str(&action)?),
+        page: Some(page.into()),
+        limit: Some(site_data.topics_per_page.into()),
+        auth: site_data.auth.clone(),
+        ..Default::default()
+    };
+    let mod_log = get_mod_log(params).await?;
     // TODO: consider moving this upstream
     let entries: Vec<Vec<ModLogEntry>> = vec![
         mod_log
             .removed_posts
             .into_iter()
             .map(|m| {
-                // TODO: why is removed an option??
-                let action = if m.mod_remove_post.removed.unwrap() {
+                let action = if flag(m.mod_remove_post.removed) {
                     "Removed"
                 } else {
                     "Restored"
                 };
-                let message = format!("{action} post [{}]({})", m.post.name, m.post.ap_id);
                 ModLogEntry {
+                    message: format!("{action} post {}", post_link(&m.post)),
                     community: Some(m.community),
+                    moderator: m.moderator,
                     reason: m.mod_remove_post.reason,
+                    expires: None,
                     when: m.mod_remove_post.when_,
-                    message,
+                }
+            })
+            .collect(),
+        mod_log
+            .locked_posts
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_lock_post.locked) {
+                    "Locked"
+                } else {
+                    "Unlocked"
+                };
+                ModLogEntry {
+                    message: format!("{action} post {}", post_link(&m.post)),
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: None,
+                    expires: None,
+                    when: m.mod_lock_post.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .featured_posts
+            .into_iter()
+            .map(|m| {
+                let featured = flag(m.mod_feature_post.featured);
+                let action = match (m.mod_feature_post.is_featured_community, featured) {
+                    (true, true) => "Stickied",
+                    (true, false) => "Unstickied",
+                    (false, true) => "Announced",
+                    (false, false) => "Unannounced",
+                };
+                ModLogEntry {
+                    message: format!("{action} post {}", post_link(&m.post)),
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: None,
+                    expires: None,
+                    when: m.mod_feature_post.when_,
                 }
             })
             .collect(),
@@ -117,7 +370,7 @@ pub async fn mod_log(site_data: SiteData) -> Result<Template, ErrorPage> {
             .removed_comments
             .into_iter()
             .map(|m| {
-                let action = if m.mod_remove_comment.removed.unwrap() {
+                let action = if flag(m.mod_remove_comment.removed) {
                     "Removed"
                 } else {
                     "Restored"
@@ -126,16 +379,216 @@ pub async fn mod_log(site_data: SiteData) -> Result<Template, ErrorPage> {
                 if content.chars().count() > 100 {
                     content = format!("{}...", content.chars().take(100).collect::<String>());
                 }
-                let message = format!("{action} comment [{}]({})", content, m.comment.ap_id);
+                let message = format!(
+                    "{action} comment [{}](/viewtopic?p={}) by {}",
+                    content,
+                    m.comment.id.0,
+                    person_link(&m.commenter)
+                );
                 ModLogEntry {
+                    message,
                     community: Some(m.community),
+                    moderator: m.moderator,
                     reason: m.mod_remove_comment.reason,
+                    expires: None,
                     when: m.mod_remove_comment.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .removed_communities
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_remove_community.removed) {
+                    "Removed"
+                } else {
+                    "Restored"
+                };
+                ModLogEntry {
+                    message: format!("{action} forum {}", forum_link(&m.community)),
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: m.mod_remove_community.reason,
+                    expires: m.mod_remove_community.expires,
+                    when: m.mod_remove_community.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .banned_from_community
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_ban_from_community.banned) {
+                    "Banned"
+                } else {
+                    "Unbanned"
+                };
+                let message = format!(
+                    "{action} {} from forum {}",
+                    person_link(&m.banned_person),
+                    forum_link(&m.community)
+                );
+                ModLogEntry {
+                    message,
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: m.mod_ban_from_community.reason,
+                    expires: m.mod_ban_from_community.expires,
+                    when: m.mod_ban_from_community.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .banned
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_ban.banned) {
+                    "Banned"
+                } else {
+                    "Unbanned"
+                };
+                ModLogEntry {
+                    message: format!("{action} {} from the site", person_link(&m.banned_person)),
+                    community: None,
+                    moderator: m.moderator,
+                    reason: m.mod_ban.reason,
+                    expires: m.mod_ban.expires,
+                    when: m.mod_ban.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .added_to_community
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_add_community.removed) {
+                    "Removed"
+                } else {
+                    "Appointed"
+                };
+                let message = format!(
+                    "{action} {} as moderator of forum {}",
+                    person_link(&m.modded_person),
+                    forum_link(&m.community)
+                );
+                ModLogEntry {
                     message,
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: None,
+                    expires: None,
+                    when: m.mod_add_community.when_,
                 }
             })
             .collect(),
+        mod_log
+            .transferred_to_community
+            .into_iter()
+            .map(|m| {
+                let message = format!(
+                    "Transferred forum {} to {}",
+                    forum_link(&m.community),
+                    person_link(&m.modded_person)
+                );
+                ModLogEntry {
+                    message,
+                    community: Some(m.community),
+                    moderator: m.moderator,
+                    reason: None,
+                    expires: None,
+                    when: m.mod_transfer_community.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .added
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_add.removed) {
+                    "Removed"
+                } else {
+                    "Appointed"
+                };
+                ModLogEntry {
+                    message: format!("{action} {} as admin", person_link(&m.modded_person)),
+                    community: None,
+                    moderator: m.moderator,
+                    reason: None,
+                    expires: None,
+                    when: m.mod_add.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .hidden_communities
+            .into_iter()
+            .map(|m| {
+                let action = if flag(m.mod_hide_community.hidden) {
+                    "Hid"
+                } else {
+                    "Unhid"
+                };
+                ModLogEntry {
+                    message: format!("{action} forum {}", forum_link(&m.community)),
+                    community: Some(m.community),
+                    moderator: m.admin,
+                    reason: m.mod_hide_community.reason,
+                    expires: None,
+                    when: m.mod_hide_community.when_,
+                }
+            })
+            .collect(),
+        mod_log
+            .admin_purged_persons
+            .into_iter()
+            .map(|m| ModLogEntry {
+                message: "Purged a user".to_string(),
+                community: None,
+                moderator: m.admin,
+                reason: m.admin_purge_person.reason,
+                expires: None,
+                when: m.admin_purge_person.when_,
+            })
+            .collect(),
+        mod_log
+            .admin_purged_communities
+            .into_iter()
+            .map(|m| ModLogEntry {
+                message: "Purged a forum".to_string(),
+                community: None,
+                moderator: m.admin,
+                reason: m.admin_purge_community.reason,
+                expires: None,
+                when: m.admin_purge_community.when_,
+            })
+            .collect(),
+        mod_log
+            .admin_purged_posts
+            .into_iter()
+            .map(|m| ModLogEntry {
+                message: format!("Purged a post from forum {}", forum_link(&m.community)),
+                community: Some(m.community),
+                moderator: m.admin,
+                reason: m.admin_purge_post.reason,
+                expires: None,
+                when: m.admin_purge_post.when_,
+            })
+            .collect(),
+        mod_log
+            .admin_purged_comments
+            .into_iter()
+            .map(|m| ModLogEntry {
+                message: format!("Purged a comment from post {}", post_link(&m.post)),
+                community: None,
+                moderator: m.admin,
+                reason: m.admin_purge_comment.reason,
+                expires: None,
+                when: m.admin_purge_comment.when_,
+            })
+            .collect(),
     ];
+    // each category is paginated separately by Lemmy, see above
+    let fetched_count = entries.iter().map(Vec::len).max().unwrap_or(0);
     let entries: Vec<_> = entries
         .into_iter()
         .flatten()
@@ -147,18 +600,433 @@ pub async fn mod_log(site_data: SiteData) -> Result<Template, ErrorPage> {
         .rev()
         .collect();
 
+    let mut base_link = format!("/mod_log?action={action}&");
+    if let Some(f) = f {
+        base_link.push_str(&format!("f={f}&"));
+    }
+    if let Some(m) = m {
+        base_link.push_str(&format!("m={m}&"));
+    }
+    let limit = PageLimit::Unknown(fetched_count, site_data.topics_per_page);
+    let pagination = Pagination::new(page, limit, base_link);
+    let actions = MOD_LOG_ACTIONS.to_vec();
     let ctx = Context::builder()
         .title(i18n_(&site_data, "mod_log_title"))
         .site_data(site_data)
-        .other(context! { entries })
+        .other(context! { entries, actions, action, f, m, pagination })
         .build();
     Ok(Template::render("site/mod_log", ctx))
 }
 
+/// Some flags in mod log entries are optional in Lemmy, those count as set if missing
+fn flag<T: Into<Option<bool>>>(flag: T) -> bool {
+    flag.into().unwrap_or(true)
+}
+
+fn person_link(person: &PersonSafe) -> String {
+    format!("[{}](/view_profile?u={})", person.name, person.id.0)
+}
+
+fn post_link(post: &Post) -> String {
+    format!("[{}](/view_topic?t={})", post.name, post.id.0)
+}
+
+fn forum_link(community: &CommunitySafe) -> String {
+    format!("[{}](/view_forum?f={})", community.title, community.id.0)
+}
+
 #[derive(Debug, Serialize)]
 pub struct ModLogEntry {
     community: Option<CommunitySafe>,
+    moderator: Option<PersonSafe>,
     reason: Option<String>,
+    expires: Option<NaiveDateTime>,
     when: NaiveDateTime,
     message: String,
 }
+
+/// Post or comment report, so that both can be shown in a single list
+#[derive(Serialize)]
+struct Report {
+    id: i32,
+    /// Either "post" or "comment"
+    kind: &'static str,
+    reason: String,
+    resolved: bool,
+    published: NaiveDateTime,
+    reporter: PersonSafe,
+    resolver: Option<PersonSafe>,
+    community: CommunitySafe,
+    /// Author of the reported content
+    creator: PersonSafe,
+    creator_banned_from_community: bool,
+    title: String,
+    /// Content at the time when it was reported
+    original_content: String,
+    link: String,
+    removed: bool,
+    /// Query string for `remove_item` route
+    remove_param: String,
+}
+
+impl From<PostReportView> for Report {
+    fn from(r: PostReportView) -> Self {
+        Report {
+            id: r.post_report.id.0,
+            kind: "post",
+            reason: r.post_report.reason,
+            resolved: r.post_report.resolved,
+            published: r.post_report.published,
+            reporter: r.creator,
+            resolver: r.resolver,
+            community: r.community,
+            creator: r.post_creator,
+            creator_banned_from_community: r.creator_banned_from_community,
+            title: r.post_report.original_post_name,
+            original_content: r.post_report.original_post_body.unwrap_or_default(),
+            link: format!("/view_topic?t={}", r.post.id),
+            removed: r.post.removed,
+            remove_param: format!("t={}", r.post.id),
+        }
+    }
+}
+
+impl From<CommentReportView> for Report {
+    fn from(r: CommentReportView) -> Self {
+        Report {
+            id: r.comment_report.id.0,
+            kind: "comment",
+            reason: r.comment_report.reason,
+            resolved: r.comment_report.resolved,
+            published: r.comment_report.published,
+            reporter: r.creator,
+            resolver: r.resolver,
+            community: r.community,
+            creator: r.comment_creator,
+            creator_banned_from_community: r.creator_banned_from_community,
+            title: format!("Re: {}", r.post.name),
+            original_content: r.comment_report.original_comment_text,
+            link: format!("/viewtopic?p={}", r.comment.id.0),
+            removed: r.comment.removed,
+            remove_param: format!("r={}", r.comment.id.0),
+        }
+    }
+}
+
+/// Reports in communities which the user moderates. Lemmy only returns reports which the user
+/// is allowed to handle, so admins see reports from all communities. Post and comment reports are
+/// shown in separate tabs, so that each can be paginated by the backend.
+#[get("/mcp/reports?<kind>&<page>&<all>")]
+pub async fn report_queue(
+    kind: Option<String>,
+    page: Option<i32>,
+    all: Option<bool>,
+    site_data: SiteData,
+) -> Result<Either<Template, Redirect>, ErrorPage> {
+    let auth = match &site_data.auth {
+        Some(auth) => auth.clone(),
+        None => return Ok(Either::Right(Redirect::to(uri!(login)))),
+    };
+    let page = page.unwrap_or(1);
+    let all = all.unwrap_or(false);
+    let limit = site_data.topics_per_page;
+    let (kind, reports): (&str, Vec<Report>) = match kind.as_deref() {
+        None | Some("post") => {
+            let reports = list_post_reports(page, limit, !all, auth)
+                .await?
+                .post_reports;
+            ("post", reports.into_iter().map(Report::from).collect())
+        }
+        Some("comment") => {
+            let reports = list_comment_reports(page, limit, !all, auth)
+                .await?
+                .comment_reports;
+            ("comment", reports.into_iter().map(Report::from).collect())
+        }
+        Some(k) => return Err(anyhow!("Invalid report type {k}").into()),
+    };
+
+    let limit = PageLimit::Unknown(reports.len(), site_data.topics_per_page);
+    let pagination = Pagination::new(page, limit, format!("/mcp/reports?kind={kind}&all={all}&"));
+    let ctx = Context::builder()
+        .title(format!(
+            "Moderator Control Panel - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { reports, kind, all, pagination })
+        .build();
+    Ok(Either::Left(Template::render("mcp/reports", ctx)))
+}
+
+#[derive(FromForm)]
+pub struct ResolveReportForm {
+    id: i32,
+    kind: String,
+    resolved: bool,
+}
+
+#[post("/mcp/resolve_report", data = "<form>")]
+pub async fn resolve_report(
+    form: Form<ResolveReportForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    match form.kind.as_str() {
+        "post" => {
+            resolve_post_report(form.id, form.resolved, auth).await?;
+        }
+        "comment" => {
+            resolve_comment_report(form.id, form.resolved, auth).await?;
+        }
+        _ => return Err(anyhow!("Invalid report type {}", form.kind).into()),
+    }
+    Ok(Redirect::to(uri!(report_queue(
+        Some(form.kind.as_str()),
+        _,
+        Some(!form.resolved)
+    ))))
+}
+
+#[derive(FromForm)]
+pub struct ReportBanForm {
+    /// Type of the reported item, to return to the right tab
+    kind: String,
+    community_id: i32,
+    person_id: i32,
+    reason: String,
+    ban: bool,
+    remove_data: bool,
+}
+
+/// Ban or unban the author of reported content from the community
+#[post("/mcp/report_ban", data = "<form>")]
+pub async fn report_ban(
+    form: Form<ReportBanForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    let reason = Some(form.reason.clone()).filter(|r| !r.is_empty());
+    ban_from_community(
+        CommunityId(form.community_id),
+        PersonId(form.person_id),
+        form.ban,
+        reason,
+        form.remove_data,
+        None,
+        auth,
+    )
+    .await?;
+    Ok(Redirect::to(uri!(report_queue(
+        Some(form.kind.as_str()),
+        _,
+        _
+    ))))
+}
+
+#[get("/forum_moderators?<f>")]
+pub async fn forum_moderators(f: i32, site_data: SiteData) -> Result<Template, ErrorPage> {
+    let community = get_community(NameOrId::Id(f), site_data.auth.clone()).await?;
+    let can_moderate = can_moderate(&site_data, &community.moderators);
+    // Lemmy only allows the top moderator and admins to transfer the forum
+    let can_transfer = site_data
+        .site
+        .my_user
+        .as_ref()
+        .map(|u| {
+            let person = &u.local_user_view.person;
+            person.admin || community.moderators.first().map(|m| m.moderator.id) == Some(person.id)
+        })
+        .unwrap_or(false);
+    let ctx = Context::builder()
+        .title(format!(
+            "Moderators - {}",
+            community.community_view.community.title
+        ))
+        .site_data(site_data)
+        .other(context! { community, can_moderate, can_transfer })
+        .build();
+    Ok(Template::render("forum_moderators", ctx))
+}
+
+#[derive(FromForm)]
+pub struct ForumModeratorsForm {
+    community_id: i32,
+    /// Set for actions on existing moderators
+    person_id: Option<i32>,
+    /// Name of the user to appoint as moderator
+    username: Option<String>,
+    action: String,
+}
+
+/// Appoint or remove a forum moderator, or transfer the forum to another moderator
+#[post("/forum_moderators", data = "<form>")]
+pub async fn do_forum_moderators(
+    form: Form<ForumModeratorsForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    let community_id = CommunityId(form.community_id);
+    let person_id = match (&form.person_id, &form.username) {
+        (Some(p), _) => PersonId(*p),
+        (None, Some(name)) => {
+            get_person(
+                NameOrId::Name(name.trim().to_string()),
+                site_data.auth.clone(),
+            )
+            .await?
+            .person_view
+            .person
+            .id
+        }
+        _ => return Err(anyhow!("One of params person_id and username needs to be set").into()),
+    };
+    match form.action.as_str() {
+        "add" => {
+            add_mod_to_community(community_id, person_id, true, auth).await?;
+        }
+        "remove" => {
+            add_mod_to_community(community_id, person_id, false, auth).await?;
+        }
+        "transfer" => {
+            transfer_community(community_id, person_id, auth).await?;
+        }
+        _ => return Err(anyhow!("Invalid action {}", form.action).into()),
+    }
+    Ok(Redirect::to(uri!(forum_moderators(form.community_id))))
+}
+
+/// Highest number of mod log pages which are searched for community bans.
+static MAX_BAN_LOG_PAGES: i64 = 10;
+
+/// Ban entries which are currently active. Entries need to be ordered from newest to oldest, so
+/// the first one per user is the current state. `ban` returns the banned user, whether it is a
+/// ban or unban, and the expiry time.
+fn active_bans<T, F>(entries: Vec<T>, now: NaiveDateTime, ban: F) -> Vec<T>
+where
+    F: Fn(&T) -> (PersonId, bool, Option<NaiveDateTime>),
+{
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|e| {
+            let (person_id, banned, expires) = ban(e);
+            seen.insert(person_id) && banned && expires.map(|e| e > now).unwrap_or(true)
+        })
+        .collect()
+}
+
+/// Lemmy has no api to list users who are banned from a community, so the current bans are
+/// taken from the mod log. Only the newest `MAX_BAN_LOG_PAGES` pages are searched, so very old
+/// bans may be missing.
+async fn community_bans(
+    community_id: CommunityId,
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<ModBanFromCommunityView>, Error> {
+    let mut entries = vec![];
+    for page in 1..=MAX_BAN_LOG_PAGES {
+        let params = GetModlog {
+            community_id: Some(community_id),
+            type_: Some(ModlogActionType::ModBanFromCommunity),
+            page: Some(page),
+            limit: Some(MAX_PAGE_ITEMS.into()),
+            auth: auth.clone(),
+            ..Default::default()
+        };
+        let page_entries = get_mod_log(params).await?.banned_from_community;
+        let fetched_count = page_entries.len();
+        entries.extend(page_entries);
+        if fetched_count < MAX_PAGE_ITEMS as usize {
+            break;
+        }
+    }
+    let now = Utc::now().naive_utc();
+    Ok(active_bans(entries, now, |m| {
+        let ban = &m.mod_ban_from_community;
+        (m.banned_person.id, flag(ban.banned), ban.expires)
+    }))
+}
+
+#[get("/forum_bans?<f>&<page>")]
+pub async fn forum_bans(
+    f: i32,
+    page: Option<i32>,
+    site_data: SiteData,
+) -> Result<Template, ErrorPage> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = site_data.topics_per_page;
+    let (community, bans) = join(
+        get_community(NameOrId::Id(f), site_data.auth.clone()),
+        community_bans(CommunityId(f), site_data.auth.clone()),
+    )
+    .await;
+    let community = community?;
+    let all_bans = bans?;
+    let last_page = ((all_bans.len() as i32 + page_size - 1) / page_size).max(1);
+    let bans: Vec<_> = all_bans
+        .into_iter()
+        .skip(((page - 1) * page_size) as usize)
+        .take(page_size as usize)
+        .collect();
+    let pagination = Pagination::new(
+        page,
+        PageLimit::Known(last_page),
+        format!("/forum_bans?f={f}&"),
+    );
+    let can_moderate = can_moderate(&site_data, &community.moderators);
+    let ctx = Context::builder()
+        .title(format!(
+            "Banned users - {}",
+            community.community_view.community.title
+        ))
+        .site_data(site_data)
+        .other(context! { community, bans, can_moderate, pagination })
+        .build();
+    Ok(Template::render("forum_bans", ctx))
+}
+
+#[derive(FromForm)]
+pub struct ForumUnbanForm {
+    community_id: i32,
+    person_id: i32,
+}
+
+#[post("/forum_unban", data = "<form>")]
+pub async fn forum_unban(
+    form: Form<ForumUnbanForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    ban_from_community(
+        CommunityId(form.community_id),
+        PersonId(form.person_id),
+        false,
+        None,
+        false,
+        None,
+        auth,
+    )
+    .await?;
+    Ok(Redirect::to(uri!(forum_bans(form.community_id, _))))
+}
+
+#[test]
+fn active_bans_uses_newest_entry() {
+    let now = Utc::now().naive_utc();
+    let hour = chrono::Duration::hours(1);
+    let entries = vec![
+        // unbanned after the ban below
+        (PersonId(1), false, None),
+        (PersonId(1), true, None),
+        (PersonId(2), true, Some(now + hour)),
+        (PersonId(3), true, Some(now - hour)),
+        (PersonId(4), true, None),
+        // older ban which was replaced
+        (PersonId(2), true, None),
+    ];
+    let bans: Vec<i32> = active_bans(entries, now, |e| *e)
+        .into_iter()
+        .map(|e| e.0 .0)
+        .collect();
+    assert_eq!(vec![2, 4], bans);
+}
@@ -1,39 +1,444 @@
 use crate::{
     api::{
-        comment::list_comments,
+        comment::{
+            get_comment,
+            like_comment,
+            list_comment_replies,
+            list_comments_page,
+            list_root_comments,
+        },
         community::get_community,
-        post::{create_post, edit_post, get_post},
+        last_reply::{get_last_reply_in_thread, invalidate_last_reply, record_post},
+        moderation::get_community_mod_log,
+        post::{create_post, edit_post, get_post, like_post},
         NameOrId,
     },
     error::ErrorPage,
-    pagination::{PageLimit, Pagination, PAGE_ITEMS},
+    pagination::{comment_page, PageLimit, Pagination},
+    read_state::{last_read_comment, mark_topic_read},
     rocket_uri_macro_login,
-    routes::CLIENT,
+    routes::{build_topic_view_cookie, comment::multiquote_items, threaded_view, CLIENT},
     site_fairing::SiteData,
-    utils::{replace_smilies, Context},
+    subscriptions::{clear_subscription_notifications, is_subscribed},
+    utils::{can_moderate, replace_smilies, Context},
+};
+use anyhow::{anyhow, Error};
+use futures::future::{join, join_all};
+use itertools::Itertools;
+use lemmy_api_common::{
+    lemmy_db_schema::{
+        newtypes::{CommentId, PostId},
+        source::person::PersonSafe,
+        ModlogActionType,
+    },
+    lemmy_db_views::structs::CommentView,
+    post::GetPostResponse,
+    sensitive::Sensitive,
 };
-use lemmy_api_common::lemmy_db_views::structs::CommentView;
 use reqwest::header::HeaderName;
-use rocket::{form::Form, response::Redirect, Either};
+use rocket::{form::Form, http::CookieJar, response::Redirect, Either};
 use rocket_dyn_templates::{context, Template};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+};
 use url::Url;
 
-#[get("/view_topic?<t>&<page>")]
+/// Comment with extra data needed for rendering it in a topic
+#[derive(Serialize)]
+pub struct TopicComment {
+    #[serde(flatten)]
+    comment_view: CommentView,
+    /// Nesting level in threaded mode, zero for top-level comments
+    depth: usize,
+    has_replies: bool,
+    /// Excerpt of the parent comment, shown in flat mode
+    in_reply_to: Option<ReplyQuote>,
+    /// Selected for multi-quote
+    quoted: bool,
+    /// Reason given by the moderator who removed the comment, only set for moderators
+    removal_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReplyQuote {
+    comment_id: CommentId,
+    creator: PersonSafe,
+    excerpt: String,
+}
+
+/// Ids of the comment's ancestors followed by its own id, taken from the comment path
+/// (eg "0.12.34").
+fn comment_path(comment: &CommentView) -> Vec<i32> {
+    comment
+        .comment
+        .path
+        .0
+        .split('.')
+        .skip(1)
+        .filter_map(|id| id.parse().ok())
+        .collect()
+}
+
+fn parent_id(comment: &CommentView) -> Option<CommentId> {
+    let path = comment_path(comment);
+    path.len()
+        .checked_sub(2)
+        .and_then(|i| path.get(i))
+        .map(|id| CommentId(*id))
+}
+
+/// Bring comments into the order in which they are shown in the topic. Comments from Lemmy are
+/// sorted by time, in threaded mode replies are placed below their parent.
+pub fn sort_comments(comments: &mut [CommentView], threaded: bool) {
+    if threaded {
+        comments.sort_by_cached_key(comment_path);
+    }
+}
+
+/// Add data for rendering to comments. `parents` are comments which are not shown on the current
+/// page, but which are needed for "in reply to" quotes.
+pub fn topic_comments(comments: &[CommentView], parents: &[CommentView]) -> Vec<TopicComment> {
+    let has_replies: HashSet<CommentId> = comments.iter().filter_map(parent_id).collect();
+    comments
+        .iter()
+        .map(|c| {
+            let in_reply_to = parent_id(c)
+                .and_then(|p| comments.iter().chain(parents).find(|c| c.comment.id == p))
+                .map(|p| {
+                    let mut excerpt = p.comment.content.replace('\n', " ");
+                    if p.comment.deleted || p.comment.removed {
+                        excerpt = "This post is no longer available.".to_string();
+                    } else if excerpt.chars().count() > 100 {
+                        excerpt = format!("{}...", excerpt.chars().take(100).collect::<String>());
+                    }
+                    ReplyQuote {
+                        comment_id: p.comment.id,
+                        creator: p.creator.clone(),
+                        excerpt,
+                    }
+                });
+            TopicComment {
+                comment_view: c.clone(),
+                depth: comment_path(c).len().saturating_sub(1),
+                has_replies: has_replies.contains(&c.comment.id),
+                in_reply_to,
+                quoted: false,
+                removal_reason: None,
+            }
+        })
+        .collect()
+}
+
+/// Fetch parents of the given comments, if they are not in the list themselves.
+async fn fetch_missing_parents(
+    comments: &[CommentView],
+    auth: Option<Sensitive<String>>,
+) -> Vec<CommentView> {
+    let missing = comments
+        .iter()
+        .filter_map(parent_id)
+        .filter(|p| !comments.iter().any(|c| &c.comment.id == p))
+        .unique_by(|p| p.0);
+    join_all(missing.map(|p| get_comment(p.0, auth.clone())))
+        .await
+        .into_iter()
+        // parent may have been deleted, then the quote is left out
+        .filter_map(Result::ok)
+        .map(|c| c.comment_view)
+        .collect()
+}
+
+/// Reasons for removal of the post and comments in the topic, taken from the newest mod log
+/// entries of the community.
+async fn removal_reasons(
+    post: &GetPostResponse,
+    auth: Option<Sensitive<String>>,
+) -> Result<(Option<String>, HashMap<CommentId, Option<String>>), Error> {
+    let community_id = post.community_view.community.id;
+    let (posts, comments) = join(
+        get_community_mod_log(community_id, ModlogActionType::ModRemovePost, auth.clone()),
+        get_community_mod_log(community_id, ModlogActionType::ModRemoveComment, auth),
+    )
+    .await;
+    // entries are ordered from newest to oldest, so the first one is the current removal
+    let post_reason = posts?
+        .removed_posts
+        .into_iter()
+        .find(|m| m.post.id == post.post_view.post.id)
+        .and_then(|m| m.mod_remove_post.reason);
+    let mut comment_reasons = HashMap::new();
+    for m in comments?.removed_comments {
+        comment_reasons
+            .entry(m.comment.id)
+            .or_insert(m.mod_remove_comment.reason);
+    }
+    Ok((post_reason, comment_reasons))
+}
+
+/// Top-level comments on the given page of a topic in threaded mode, together with all their
+/// replies. Each page shows `page_size` top-level comments, so that threads are never split
+/// between pages.
+async fn threaded_page_comments(
+    roots: &[CommentView],
+    page: i32,
+    page_size: i32,
+    auth: Option<Sensitive<String>>,
+) -> Result<Vec<CommentView>, Error> {
+    let page_roots: Vec<CommentView> = roots
+        .iter()
+        .skip(((page - 1) * page_size) as usize)
+        .take(page_size as usize)
+        .cloned()
+        .collect();
+    let replies = join_all(
+        page_roots
+            .iter()
+            .map(|c| list_comment_replies(c.comment.id, auth.clone())),
+    )
+    .await;
+    let mut comments = page_roots;
+    for r in replies {
+        comments.extend(r?);
+    }
+    sort_comments(&mut comments, true);
+    Ok(comments)
+}
+
+/// Page on which the comment is shown in threaded mode, which is the page of its top-level
+/// comment. `roots` are all top-level comments in the topic.
+fn threaded_comment_page(
+    comment: &CommentView,
+    roots: &[CommentView],
+    page_size: i32,
+) -> Option<i32> {
+    let root = CommentId(*comment_path(comment).first()?);
+    comment_page(root, roots, page_size)
+}
+
+/// Number of pages in a topic. The comment count from post aggregates doesn't include deleted and
+/// removed comments, but these are still paginated, so the count only gives the lowest possible
+/// last page. Pages after it are fetched until one is not full. `current` is the number of the
+/// current page and its number of comments, if it was already fetched.
+async fn topic_last_page(
+    post_id: PostId,
+    comment_count: i64,
+    page_size: i32,
+    current: Option<(i32, usize)>,
+    auth: Option<Sensitive<String>>,
+) -> Result<i32, Error> {
+    find_last_page(comment_count, page_size, current, |page| {
+        let auth = auth.clone();
+        async move {
+            Ok(list_comments_page(post_id, page, page_size, false, auth)
+                .await?
+                .len())
+        }
+    })
+    .await
+}
+
+/// Implementation of `topic_last_page`, `page_len` returns the number of comments on a page.
+async fn find_last_page<F, Fut>(
+    comment_count: i64,
+    page_size: i32,
+    current: Option<(i32, usize)>,
+    page_len: F,
+) -> Result<i32, Error>
+where
+    F: Fn(i32) -> Fut,
+    Fut: Future<Output = Result<usize, Error>>,
+{
+    let estimate = ((comment_count as i32 + page_size - 1) / page_size).max(1);
+    let mut last_page = estimate;
+    if let Some((page, items)) = current {
+        match items as i32 {
+            0 => {}
+            n if n < page_size => return Ok(page),
+            _ => last_page = last_page.max(page),
+        }
+    }
+    loop {
+        let items = match current {
+            Some((page, items)) if page == last_page => items,
+            _ => page_len(last_page).await?,
+        };
+        match items as i32 {
+            // previous page was full, or this is the first page
+            0 => return Ok((last_page - 1).max(1)),
+            n if n < page_size => return Ok(last_page),
+            _ => last_page += 1,
+        }
+    }
+}
+
+/// Find the first comment in chronological order for which `is_after` returns true, and the page
+/// where it is shown. `is_after` must be false for all comments before that one, and true for all
+/// comments after it. This uses binary search, so only a few pages need to be fetched even for
+/// very long topics.
+async fn find_comment_page<F: Fn(&CommentView) -> bool>(
+    post_id: PostId,
+    last_page: i32,
+    page_size: i32,
+    is_after: F,
+    auth: Option<Sensitive<String>>,
+) -> Result<Option<(i32, CommentView)>, Error> {
+    search_pages(last_page, is_after, |page| {
+        list_comments_page(post_id, page, page_size, false, auth.clone())
+    })
+    .await
+}
+
+/// Implementation of `find_comment_page`, `fetch_page` returns the items on a page.
+async fn search_pages<T, P, F, Fut>(
+    last_page: i32,
+    is_after: P,
+    fetch_page: F,
+) -> Result<Option<(i32, T)>, Error>
+where
+    T: Clone,
+    P: Fn(&T) -> bool,
+    F: Fn(i32) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, Error>>,
+{
+    let (mut low, mut high) = (1, last_page);
+    let mut found = None;
+    while low <= high {
+        let page = (low + high) / 2;
+        let items = fetch_page(page).await?;
+        if items.is_empty() {
+            high = page - 1;
+            continue;
+        }
+        match items.iter().position(&is_after) {
+            None => low = page + 1,
+            Some(i) => {
+                found = Some((page, items[i].clone()));
+                if i > 0 {
+                    break;
+                }
+                // item is first on the page, so it may continue on earlier pages
+                high = page - 1;
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[get("/view_topic?<t>&<page>&<view>")]
 pub async fn view_topic(
     t: i32,
     page: Option<i32>,
+    view: Option<String>,
     site_data: SiteData,
-) -> Result<Template, ErrorPage> {
+    cookies: &CookieJar<'_>,
+) -> Result<Either<Template, Redirect>, ErrorPage> {
     let post = get_post(t, site_data.auth.clone()).await?;
+    let post_id = post.post_view.post.id;
+    let comment_count = post.post_view.counts.comments;
+    let auth = site_data.auth.clone();
+    let threaded = threaded_view(cookies);
+    let page = page.unwrap_or(1);
+    let page_size = site_data.posts_per_page;
 
-    let all_comments = list_comments(post.post_view.post.id, site_data.auth.clone()).await?;
-    let page_comments: Vec<CommentView> = all_comments
-        .iter()
-        // select items for current page
-        .skip(((page.unwrap_or(1) - 1) * PAGE_ITEMS) as usize)
-        .take(PAGE_ITEMS as usize)
-        .cloned()
+    // threaded mode is paginated by top-level comments, because comments are sorted by their
+    // position in the tree and Lemmy can only paginate in chronological order. Otherwise only the
+    // current page is fetched.
+    let (comments, roots, last_page) = if threaded {
+        let roots = list_root_comments(post_id, auth.clone()).await?;
+        let comments = threaded_page_comments(&roots, page, page_size, auth.clone()).await?;
+        let last_page = ((roots.len() as i32 + page_size - 1) / page_size).max(1);
+        (comments, Some(roots), last_page)
+    } else {
+        let comments = list_comments_page(post_id, page, page_size, false, auth.clone()).await?;
+        let current = Some((page, comments.len()));
+        let last_page =
+            topic_last_page(post_id, comment_count, page_size, current, auth.clone()).await?;
+        (comments, None, last_page)
+    };
+    let parents = if threaded {
+        vec![]
+    } else {
+        fetch_missing_parents(&comments, auth.clone()).await
+    };
+
+    // moderators can see removed content, together with the reason for removal
+    let can_moderate = can_moderate(&site_data, &post.moderators);
+    let has_removed = post.post_view.post.removed || comments.iter().any(|c| c.comment.removed);
+    let (post_removal_reason, comment_removal_reasons) = if can_moderate && has_removed {
+        removal_reasons(&post, auth.clone()).await?
+    } else {
+        (None, HashMap::new())
+    };
+
+    let hide_post_content = post.post_view.post.removed && !can_moderate;
+
+    let multiquote = multiquote_items(cookies, t);
+    let page_comments: Vec<TopicComment> = topic_comments(&comments, &parents)
+        .into_iter()
+        .map(|mut c| {
+            c.quoted = multiquote.contains(&format!("c{}", c.comment_view.comment.id.0));
+            if c.comment_view.comment.removed {
+                c.removal_reason = comment_removal_reasons
+                    .get(&c.comment_view.comment.id)
+                    .cloned()
+                    .flatten();
+            }
+            c
+        })
         .collect();
+    let post_quoted = multiquote.contains(&format!("p{t}"));
+
+    // link to first comment which the user hasn't seen yet, then mark the shown comments as read
+    let mut first_unread = None;
+    let mut subscribed = false;
+    if let Some(my_user) = &site_data.site.my_user {
+        let person_id = my_user.local_user_view.person.id;
+        subscribed = is_subscribed(person_id, post_id);
+        let last_read = last_read_comment(person_id, post_id);
+        if let Some(last_read) = last_read {
+            let last_reply = get_last_reply_in_thread(&post.post_view, auth.clone()).await?;
+            let has_unread = last_reply
+                .comment_id
+                .map(|c| c.0 > last_read.0)
+                .unwrap_or(false);
+            // comment ids are increasing, so they can also be used to search chronologically
+            let is_unread = |c: &CommentView| c.comment.id.0 > last_read.0;
+            let found = match &roots {
+                _ if !has_unread => None,
+                Some(roots) => {
+                    // search chronologically, then show the page with its thread
+                    let flat_last_page =
+                        topic_last_page(post_id, comment_count, page_size, None, auth.clone())
+                            .await?;
+                    find_comment_page(post_id, flat_last_page, page_size, is_unread, auth.clone())
+                        .await?
+                        .map(|(_, c)| {
+                            let page = threaded_comment_page(&c, roots, page_size).unwrap_or(1);
+                            (page, c.comment.id)
+                        })
+                }
+                None => find_comment_page(post_id, last_page, page_size, is_unread, auth.clone())
+                    .await?
+                    .map(|(page, c)| (page, c.comment.id)),
+            };
+            first_unread =
+                found.map(|(page, id)| format!("/view_topic?t={t}&page={page}#p{}", id.0));
+        }
+        if view.as_deref() == Some("unread") {
+            let link = match (last_read, &first_unread) {
+                // topic was never opened, so start at the beginning
+                (None, _) => format!("/view_topic?t={t}"),
+                (Some(_), Some(first_unread)) => first_unread.clone(),
+                (Some(_), None) => format!("/view_topic?t={t}&page={last_page}"),
+            };
+            return Ok(Either::Right(Redirect::to(link)));
+        }
+        let last_shown = comments.iter().map(|c| c.comment.id).max_by_key(|c| c.0);
+        mark_topic_read(person_id, post_id, last_shown);
+        clear_subscription_notifications(person_id, Some(post_id));
+    }
 
     // determine if post.url should be rendered as <img> or <a href>
     let mut is_image_url = false;
@@ -43,15 +448,126 @@ pub async fn view_topic(
         let content_type = &image.headers()[HeaderName::from_static("content-type")];
         is_image_url = content_type.to_str()?.starts_with("image/");
     }
-    let limit = PageLimit::Known((all_comments.len() as f32 / PAGE_ITEMS as f32).ceil() as i32);
-    let pagination = Pagination::new(page.unwrap_or(1), limit, format!("/viewtopic?t={t}&"));
+    let limit = PageLimit::Known(last_page);
+    let pagination = Pagination::new(page, limit, format!("/view_topic?t={t}&"));
 
     let ctx = Context::builder()
         .title(post.post_view.post.name.clone())
         .site_data(site_data)
-        .other(context! { post, is_image_url, page_comments, all_comments, pagination })
+        .other(context! {
+            post,
+            is_image_url,
+            page_comments,
+            first_unread,
+            threaded,
+            multiquote,
+            post_quoted,
+            can_moderate,
+            hide_post_content,
+            post_removal_reason,
+            subscribed,
+            pagination
+        })
         .build();
-    Ok(Template::render("view_topic", ctx))
+    Ok(Either::Left(Template::render("view_topic", ctx)))
+}
+
+/// Switch between flat and threaded display of comments
+#[get("/topic_view_mode?<t>&<threaded>")]
+pub async fn topic_view_mode(t: i32, threaded: bool, cookies: &CookieJar<'_>) -> Redirect {
+    cookies.add(build_topic_view_cookie(threaded));
+    Redirect::to(uri!(view_topic(t, _, _)))
+}
+
+/// Returns link to the page of the topic where the comment is shown.
+pub async fn comment_link(
+    comment_id: i32,
+    threaded: bool,
+    page_size: i32,
+    auth: Option<Sensitive<String>>,
+) -> Result<String, Error> {
+    let comment = get_comment(comment_id, auth.clone()).await?.comment_view;
+    let post_id = comment.post.id;
+    let page = if threaded {
+        let roots = list_root_comments(post_id, auth).await?;
+        threaded_comment_page(&comment, &roots, page_size)
+    } else {
+        let comment_count = get_post(post_id.0, auth.clone())
+            .await?
+            .post_view
+            .counts
+            .comments;
+        let last_page =
+            topic_last_page(post_id, comment_count, page_size, None, auth.clone()).await?;
+        let key = (comment.comment.published, comment.comment.id.0);
+        let is_after = |c: &CommentView| (c.comment.published, c.comment.id.0) >= key;
+        find_comment_page(post_id, last_page, page_size, is_after, auth)
+            .await?
+            .map(|(page, _)| page)
+    };
+    // comment may be deleted, then just go to first page
+    let page = page.unwrap_or(1);
+    Ok(format!("/view_topic?t={post_id}&page={page}#p{comment_id}"))
+}
+
+/// Jump to a single comment, on the right page of its topic
+#[get("/viewtopic?<p>")]
+pub async fn view_comment(
+    p: i32,
+    site_data: SiteData,
+    cookies: &CookieJar<'_>,
+) -> Result<Redirect, ErrorPage> {
+    let link = comment_link(
+        p,
+        threaded_view(cookies),
+        site_data.posts_per_page,
+        site_data.auth,
+    )
+    .await?;
+    Ok(Redirect::to(link))
+}
+
+#[derive(FromForm)]
+pub struct VoteForm {
+    t: Option<i32>,
+    r: Option<i32>,
+    /// 1 to like, -1 to dislike and 0 to remove the vote
+    score: i16,
+}
+
+/// Like or dislike a topic or reply, then go back to it
+#[post("/vote", data = "<form>")]
+pub async fn vote(
+    form: Form<VoteForm>,
+    site_data: SiteData,
+    cookies: &CookieJar<'_>,
+) -> Result<Redirect, ErrorPage> {
+    let auth = match &site_data.auth {
+        Some(auth) => auth.clone(),
+        None => return Ok(Redirect::to(uri!(login))),
+    };
+    let enable_downvotes = site_data.site.site_view.local_site.enable_downvotes;
+    if !(0..=1).contains(&form.score) && !(form.score == -1 && enable_downvotes) {
+        return Err(anyhow!("Invalid vote score {}", form.score).into());
+    }
+    match (form.t, form.r) {
+        (Some(t), None) => {
+            like_post(t, form.score, auth).await?;
+            Ok(Redirect::to(format!("/view_topic?t={t}#p1")))
+        }
+        (None, Some(r)) => {
+            like_comment(r, form.score, auth).await?;
+            let link = comment_link(
+                r,
+                threaded_view(cookies),
+                site_data.posts_per_page,
+                site_data.auth,
+            )
+            .await?;
+            Ok(Redirect::to(link))
+        }
+        _ => Err(anyhow!("One of params t and r needs to be set").into()),
+    }
 }
 
 #[get("/post_editor?<f>&<edit>")]
@@ -132,11 +648,82 @@ pub async fn do_post(
 
     let auth = site_data.auth.expect("user not logged in");
     let post = match edit {
-        None => create_post(subject, message, f, auth).await?,
-        Some(e) => edit_post(subject, message, e, auth).await?,
+        None => {
+            let post = create_post(subject, message, f, auth).await?;
+            record_post(&post.post_view);
+            mark_topic_read(post.post_view.creator.id, post.post_view.post.id, None);
+            post
+        }
+        Some(e) => {
+            let post = edit_post(subject, message, e, auth).await?;
+            // title may have changed
+            invalidate_last_reply(post.post_view.post.id, post.post_view.community.id);
+            post
+        }
     };
     Ok(Either::Right(Redirect::to(uri!(view_topic(
         post.post_view.post.id.0,
-        Some(1)
+        Some(1),
+        _
     )))))
 }
+
+#[cfg(test)]
+fn test_pages(items: i32, page_size: i32) -> Vec<Vec<i32>> {
+    (1..=items)
+        .collect::<Vec<_>>()
+        .chunks(page_size as usize)
+        .map(<[i32]>::to_vec)
+        .collect()
+}
+
+#[cfg(test)]
+#[actix_rt::test]
+async fn find_last_page_probes_past_estimate() {
+    let pages = test_pages(45, 10);
+    let page_len = |page: i32| {
+        let len = pages.get(page as usize - 1).map(Vec::len).unwrap_or(0);
+        async move { Ok(len) }
+    };
+    // count is exact
+    assert_eq!(5, find_last_page(45, 10, None, page_len).await.unwrap());
+    // deleted comments are not counted, but still paginated
+    assert_eq!(5, find_last_page(21, 10, None, page_len).await.unwrap());
+    // current page is not full, so it is the last one
+    assert_eq!(
+        5,
+        find_last_page(21, 10, Some((5, 5)), page_len)
+            .await
+            .unwrap()
+    );
+    // topic is empty
+    let empty = |_| async { Ok(0) };
+    assert_eq!(1, find_last_page(0, 10, None, empty).await.unwrap());
+    // last page is exactly full
+    let full = test_pages(40, 10);
+    let full_len = |page: i32| {
+        let len = full.get(page as usize - 1).map(Vec::len).unwrap_or(0);
+        async move { Ok(len) }
+    };
+    assert_eq!(4, find_last_page(40, 10, None, full_len).await.unwrap());
+}
+
+#[cfg(test)]
+#[actix_rt::test]
+async fn search_pages_finds_first_match() {
+    let pages = test_pages(95, 10);
+    let fetch_page = |page: i32| {
+        let items = pages.get(page as usize - 1).cloned().unwrap_or_default();
+        async move { Ok(items) }
+    };
+    for first in [1, 10, 11, 42, 95] {
+        let found = search_pages(10, |i: &i32| *i >= first, fetch_page)
+            .await
+            .unwrap();
+        assert_eq!(Some(((first - 1) / 10 + 1, first)), found);
+    }
+    let none = search_pages(10, |i: &i32| *i > 95, fetch_page)
+        .await
+        .unwrap();
+    assert_eq!(None, none);
+}
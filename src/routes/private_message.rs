@@ -56,7 +56,7 @@ pub async fn private_messages_list(site_data: SiteData) -> Result<Template, Erro
         .person
         .id;
     let auth = site_data.auth.clone().unwrap();
-    let private_message_threads: Vec<_> = list_private_messages(false, auth)
+    let private_message_threads: Vec<_> = list_private_messages(false, None, None, auth)
         .await?
         .private_messages
         .into_iter()
@@ -105,13 +105,14 @@ pub async fn private_messages_thread(u: i32, site_data: SiteData) -> Result<Temp
     let other_user_id = PersonId(u);
     let auth = site_data.auth.clone().unwrap();
     // TODO: would be nice if lemmy api could query PMs involving given user
-    let private_messages: Vec<PrivateMessageView> = list_private_messages(false, auth.clone())
-        .await?
-        .private_messages
-        .into_iter()
-        .filter(|pm| pm.creator.id == other_user_id || pm.recipient.id == other_user_id)
-        .sorted_by_key(|pm| pm.private_message.published)
-        .collect();
+    let private_messages: Vec<PrivateMessageView> =
+        list_private_messages(false, None, None, auth.clone())
+            .await?
+            .private_messages
+            .into_iter()
+            .filter(|pm| pm.creator.id == other_user_id || pm.recipient.id == other_user_id)
+            .sorted_by_key(|pm| pm.private_message.published)
+            .collect();
 
     // mark as read
     let my_user_id = site_data
@@ -2,22 +2,26 @@ use crate::{
     api::{
         categories::get_categories,
         community::list_communities,
-        extra::{get_last_reply_in_community, PostOrComment},
+        last_reply::{get_last_replies_in_threads, get_last_reply_in_community, PostOrComment},
+        post::list_active_posts,
         site::create_site,
         user::register,
     },
     env::lemmy_backend,
     forward_get_request,
-    pagination::{PageLimit, Pagination},
+    pagination::{PageLimit, Pagination, MAX_PAGE_ITEMS},
+    read_state::{is_topic_unread, read_tracking_since},
+    rocket_uri_macro_login,
     routes::{backend_endpoints::AcceptHeader, build_jwt_cookie, user::RegisterForm, ErrorPage},
     site_fairing::SiteData,
-    utils::{main_site_title, Context},
+    utils::{can_create_community, main_site_title, Context},
     BackendResponse,
 };
 use anyhow::Error;
 use futures::future::join_all;
 use lemmy_api_common::{
     lemmy_db_schema::{source::local_site::RegistrationMode, ListingType},
+    lemmy_db_views::structs::PostView,
     lemmy_db_views_actor::structs::CommunityView,
 };
 use rocket::{form::Form, http::CookieJar, response::Redirect, Either};
@@ -41,8 +45,8 @@ pub async fn index(
         ));
     }
 
-    match get_categories(site_data.auth.clone()).await {
-        Ok(categories) => {
+    match get_categories(site_data.auth.clone()).await? {
+        Some(categories) => {
             let ctx = Context::builder()
                 .title(main_site_title(&site_data.site))
                 .site_data(site_data)
@@ -50,10 +54,8 @@ pub async fn index(
                 .build();
             Ok(Left(Right(Template::render("site/index", ctx))))
         }
-        Err(e) => {
-            warn!("{}", e);
-            Ok(Left(Left(Redirect::to(uri!("/community_list")))))
-        }
+        // no categories configured
+        None => Ok(Left(Left(Redirect::to(uri!("/community_list"))))),
     }
 }
 
@@ -67,25 +69,27 @@ pub async fn community_list(
     let listing_type: ListingType = mode
         .map(ListingType::from_str)
         .unwrap_or(Ok(ListingType::All))?;
-    let mut communities: Vec<CommunityView> = list_communities(listing_type, page, auth.clone())
-        .await?
-        .communities;
+    let mut communities: Vec<CommunityView> =
+        list_communities(listing_type, page, site_data.topics_per_page, auth.clone())
+            .await?
+            .communities;
     communities.sort_unstable_by_key(|c| c.community.id.0);
     let last_replies = join_all(
         communities
             .iter()
-            .map(|c| get_last_reply_in_community(c.community.id, auth.clone())),
+            .map(|c| get_last_reply_in_community(c, auth.clone())),
     )
     .await
     .into_iter()
     .collect::<Result<Vec<Option<PostOrComment>>, Error>>()?;
 
-    let limit = PageLimit::Unknown(communities.len());
+    let limit = PageLimit::Unknown(communities.len(), site_data.topics_per_page);
     let pagination = Pagination::new(page.unwrap_or(1), limit, "/community_list?");
+    let can_create_forum = can_create_community(&site_data);
     let ctx = Context::builder()
         .title(main_site_title(&site_data.site))
         .site_data(site_data)
-        .other(context! { communities, last_replies, pagination })
+        .other(context! { communities, last_replies, pagination, can_create_forum })
         .build();
     Ok(Either::Right(Template::render("site/community_list", ctx)))
 }
@@ -109,6 +113,7 @@ pub struct SetupForm {
     pub email: Option<String>,
     pub site_name: String,
     pub site_description: Option<String>,
+    pub registration_mode: String,
 }
 
 #[post("/setup", data = "<form>")]
@@ -129,7 +134,7 @@ pub async fn do_setup(
     create_site(
         form.site_name.clone(),
         form.site_description.clone(),
-        RegistrationMode::RequireApplication,
+        RegistrationMode::from_str(&form.registration_mode)?,
         jwt,
     )
     .await?;
@@ -139,7 +144,7 @@ pub async fn do_setup(
 
 #[get("/legal")]
 pub async fn legal(site_data: SiteData) -> Result<Template, ErrorPage> {
-    let message = site_data
+    let legal_information = site_data
         .site
         .site_view
         .local_site
@@ -148,9 +153,9 @@ pub async fn legal(site_data: SiteData) -> Result<Template, ErrorPage> {
     let ctx = Context::builder()
         .title(format!("Legal - {}", site_data.site.site_view.site.name))
         .site_data(site_data)
-        .other(context! { message })
+        .other(context! { legal_information })
         .build();
-    Ok(Template::render("message", ctx))
+    Ok(Template::render("site/legal", ctx))
 }
 
 #[get("/search?<keywords>")]
@@ -170,3 +175,69 @@ pub async fn search(keywords: String, site_data: SiteData) -> Result<Template, E
         .build();
     Ok(Template::render("site/search", ctx))
 }
+
+/// Highest number of pages with active topics which are searched for unread ones.
+static MAX_UNREAD_SCAN_PAGES: i32 = 10;
+
+/// Topics with posts which the user hasn't seen yet. Read state is only known locally, so active
+/// topics are fetched from the backend until the current page is filled.
+#[get("/unread?<page>")]
+pub async fn unread(
+    page: Option<i32>,
+    site_data: SiteData,
+) -> Result<Either<Redirect, Template>, ErrorPage> {
+    let person_id = match &site_data.site.my_user {
+        Some(my_user) => my_user.local_user_view.person.id,
+        None => return Ok(Either::Left(Redirect::to(uri!(login)))),
+    };
+    let auth = site_data.auth.clone();
+    let page = page.unwrap_or(1).max(1);
+    let page_size = site_data.topics_per_page as usize;
+    let skip = (page as usize - 1) * page_size;
+    let mut unread = vec![];
+    if let Some(since) = read_tracking_since(person_id) {
+        for backend_page in 1..=MAX_UNREAD_SCAN_PAGES {
+            let posts = list_active_posts(backend_page, MAX_PAGE_ITEMS, auth.clone())
+                .await?
+                .posts;
+            let fetched_count = posts.len();
+            // topics are sorted by newest activity (after featured ones), so once a topic has no
+            // activity since tracking started, the following ones don't either
+            let reached_read = posts
+                .last()
+                .map(|p| p.counts.newest_comment_time <= since)
+                .unwrap_or(true);
+            let posts: Vec<PostView> = posts
+                .into_iter()
+                .filter(|p| p.counts.newest_comment_time > since)
+                .collect();
+            let last_replies = get_last_replies_in_threads(&posts, auth.clone()).await?;
+            unread.extend(
+                posts
+                    .into_iter()
+                    .zip(last_replies.into_iter())
+                    .filter(|(p, l)| is_topic_unread(person_id, p.community.id, l)),
+            );
+            if unread.len() >= skip + page_size
+                || reached_read
+                || fetched_count < MAX_PAGE_ITEMS as usize
+            {
+                break;
+            }
+        }
+    }
+    let (posts, last_replies): (Vec<PostView>, Vec<PostOrComment>) =
+        unread.into_iter().skip(skip).take(page_size).unzip();
+
+    let limit = PageLimit::Unknown(posts.len(), site_data.topics_per_page);
+    let pagination = Pagination::new(page, limit, "/unread?");
+    let ctx = Context::builder()
+        .title(format!(
+            "View unread posts - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { posts, last_replies, pagination })
+        .build();
+    Ok(Either::Right(Template::render("site/unread", ctx)))
+}
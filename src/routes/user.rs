@@ -1,24 +1,56 @@
 use crate::{
     api,
     api::{
+        comment::{list_newest_comments, save_comment},
+        community::{ban_from_community, get_community},
+        extra::{list_notifications, NotificationKind},
         image::upload_image,
+        post::{get_post, save_post},
+        private_message::mark_private_message_read,
         user::{
             ban_user,
             change_password,
             get_captcha,
             get_person,
+            get_saved,
             mark_all_as_read,
+            mark_mention_read,
+            mark_reply_read,
             save_settings,
         },
         NameOrId,
     },
-    routes::{auth, build_jwt_cookie, ErrorPage},
+    env::{posts_per_page, topics_per_page},
+    pagination::{PageLimit, Pagination, MAX_PAGE_ITEMS},
+    rocket_uri_macro_forum_bans,
+    routes::{build_jwt_cookie, ErrorPage},
     site_fairing::SiteData,
+    subscriptions::{
+        clear_subscription_notifications,
+        list_subscriptions,
+        mark_subscription_notification_read,
+        subscribe_topic,
+        subscription_notifications,
+        unsubscribe_topic,
+    },
     template_helpers::i18n_,
+    user_prefs::{get_user_prefs, save_user_prefs, UserPrefs},
     utils::{empty_to_opt, main_site_title, Context},
     ALL_LANGUAGES,
 };
+use anyhow::{anyhow, Error};
+use chrono::{Duration, Utc};
+use futures::future::join;
 use lemmy_api_common::{
+    lemmy_db_schema::newtypes::{
+        CommentId,
+        CommentReplyId,
+        CommunityId,
+        PersonId,
+        PersonMentionId,
+        PostId,
+        PrivateMessageId,
+    },
     person::{ChangePassword, SaveUserSettings},
     sensitive::Sensitive,
 };
@@ -30,6 +62,7 @@ use rocket::{
     Either,
 };
 use rocket_dyn_templates::{context, Template};
+use std::cmp::Reverse;
 
 #[get("/login")]
 pub async fn login(site_data: SiteData) -> Result<Template, ErrorPage> {
@@ -128,11 +161,82 @@ pub async fn logout(cookies: &CookieJar<'_>) -> Result<Redirect, ErrorPage> {
 }
 
 #[post("/mark_all_notifications_read")]
-pub async fn mark_all_notifications_read(cookies: &CookieJar<'_>) -> Result<Redirect, ErrorPage> {
-    mark_all_as_read(auth(cookies).unwrap()).await?;
+pub async fn mark_all_notifications_read(site_data: SiteData) -> Result<Redirect, ErrorPage> {
+    mark_all_as_read(site_data.auth.unwrap()).await?;
+    if let Some(my_user) = site_data.site.my_user {
+        clear_subscription_notifications(my_user.local_user_view.person.id, None);
+    }
     Ok(Redirect::to(uri!("/")))
 }
 
+/// All notifications of the user, including already read ones if `all` is set
+#[get("/notifications?<page>&<all>")]
+pub async fn notifications(
+    page: Option<i32>,
+    all: Option<bool>,
+    site_data: SiteData,
+) -> Result<Either<Template, Redirect>, ErrorPage> {
+    let (person_id, auth) = match (&site_data.site.my_user, &site_data.auth) {
+        (Some(my_user), Some(auth)) => (my_user.local_user_view.person.id, auth.clone()),
+        _ => return Ok(Either::Right(Redirect::to(uri!(login)))),
+    };
+    let page = page.unwrap_or(1);
+    let all = all.unwrap_or(false);
+    let page_size = site_data.topics_per_page;
+    let (mut notifications, fetched_count) =
+        list_notifications(person_id, !all, page, page_size, auth).await?;
+    // new replies in subscribed topics are stored by lemmyBB and always unread
+    let subscriptions = subscription_notifications(person_id, page, page_size);
+    let fetched_count = fetched_count.max(subscriptions.len());
+    notifications.extend(subscriptions);
+    notifications.sort_by_key(|n| Reverse(n.time));
+    let limit = PageLimit::Unknown(fetched_count, page_size);
+    let pagination = Pagination::new(page, limit, format!("/notifications?all={all}&"));
+    let ctx = Context::builder()
+        .title(format!(
+            "Notifications - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { notifications, all, page, pagination })
+        .build();
+    Ok(Either::Left(Template::render("user/notifications", ctx)))
+}
+
+#[derive(FromForm)]
+pub struct NotificationReadForm {
+    kind: NotificationKind,
+    id: i32,
+    page: Option<i32>,
+    all: Option<bool>,
+}
+
+#[post("/mark_notification_read", data = "<form>")]
+pub async fn mark_notification_read(
+    form: Form<NotificationReadForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let (person_id, auth) = match (&site_data.site.my_user, site_data.auth) {
+        (Some(my_user), Some(auth)) => (my_user.local_user_view.person.id, auth),
+        _ => return Err(anyhow!("not_logged_in").into()),
+    };
+    match form.kind {
+        NotificationKind::Mention => {
+            mark_mention_read(PersonMentionId(form.id), auth).await?;
+        }
+        NotificationKind::Reply => {
+            mark_reply_read(CommentReplyId(form.id), auth).await?;
+        }
+        NotificationKind::PrivateMessage => {
+            mark_private_message_read(PrivateMessageId(form.id), auth).await?;
+        }
+        NotificationKind::Subscription => {
+            mark_subscription_notification_read(person_id, CommentId(form.id));
+        }
+    }
+    Ok(Redirect::to(uri!(notifications(form.page, form.all))))
+}
+
 #[get("/view_profile?<u>")]
 pub async fn view_profile(u: i32, site_data: SiteData) -> Result<Template, ErrorPage> {
     let person = get_person(NameOrId::Id(u), site_data.auth.clone()).await?;
@@ -161,6 +265,10 @@ pub struct EditProfileForm<'r> {
     pub new_password: String,
     pub confirm_password: String,
     pub cur_password: String,
+    /// Empty to use board default
+    pub topics_per_page: Option<i32>,
+    /// Empty to use board default
+    pub posts_per_page: Option<i32>,
 }
 
 #[get("/edit_profile")]
@@ -168,10 +276,24 @@ pub async fn edit_profile(site_data: SiteData) -> Result<Template, ErrorPage> {
     let mut all_languages = ALL_LANGUAGES.to_vec();
     let l = i18n_(&site_data, "browser_default_language");
     all_languages.push(("browser", &l));
+    let user_prefs = site_data
+        .site
+        .my_user
+        .as_ref()
+        .map(|u| get_user_prefs(u.local_user_view.person.id))
+        .unwrap_or_default();
+    let default_topics_per_page = topics_per_page();
+    let default_posts_per_page = posts_per_page();
     let ctx = Context::builder()
         .title("Edit profile")
         .site_data(site_data)
-        .other(context! { all_languages })
+        .other(context! {
+            all_languages,
+            user_prefs,
+            default_topics_per_page,
+            default_posts_per_page,
+            max_page_items: MAX_PAGE_ITEMS
+        })
         .build();
     Ok(Template::render("user/edit_profile", ctx))
 }
@@ -211,6 +333,15 @@ pub async fn do_edit_profile(
         };
         change_password(params).await?;
     }
+    if let Some(my_user) = &site_data.site.my_user {
+        let person_id = my_user.local_user_view.person.id;
+        let prefs = UserPrefs {
+            topics_per_page: form.topics_per_page.map(|n| n.clamp(1, MAX_PAGE_ITEMS)),
+            posts_per_page: form.posts_per_page.map(|n| n.clamp(1, MAX_PAGE_ITEMS)),
+            ..get_user_prefs(person_id)
+        };
+        save_user_prefs(person_id, prefs)?;
+    }
     let message = i18n_(&site_data, "settings_updated");
     let ctx = Context::builder()
         .title(message.clone())
@@ -220,27 +351,260 @@ pub async fn do_edit_profile(
     Ok(Template::render("message", ctx))
 }
 
-#[get("/ban_user?<u>")]
-pub async fn ban_form(u: i32, site_data: SiteData) -> Result<Template, ErrorPage> {
+/// Admins can ban users from the whole site, and moderators from the forums which they moderate.
+/// If `f` is set, banning from that forum is preselected.
+#[get("/ban_user?<u>&<f>")]
+pub async fn ban_form(u: i32, f: Option<i32>, site_data: SiteData) -> Result<Template, ErrorPage> {
     let person = get_person(NameOrId::Id(u), site_data.auth.clone()).await?;
+    // the forum might not be moderated by the current user, so fetch it to show its name
+    let community = match f {
+        Some(f) => Some(
+            get_community(NameOrId::Id(f), site_data.auth.clone())
+                .await?
+                .community_view
+                .community,
+        ),
+        None => None,
+    };
     let ctx = Context::builder()
         .title(i18n_(&site_data, "ban_user"))
         .site_data(site_data)
-        .other(context! { person })
+        .other(context! { person, community })
         .build();
     Ok(Template::render("user/ban_form", ctx))
 }
 
+/// Longest ban which can be given with a duration, in days. Longer bans need to be permanent.
+static MAX_BAN_DAYS: i64 = 3650;
+
+#[derive(FromFormField, Debug)]
+pub enum BanDurationUnit {
+    Hours,
+    Days,
+    Permanent,
+}
+
 #[derive(FromForm, Debug)]
 pub struct BanUserForm {
     user_id: i32,
+    /// Ban from this forum, or from the whole site if empty
+    community_id: Option<i32>,
     reason: String,
     remove_data: bool,
+    /// Number of hours or days, depending on `duration_unit`
+    duration: Option<i64>,
+    duration_unit: BanDurationUnit,
+}
+
+impl BanUserForm {
+    /// Unix timestamp when the ban expires, or None for permanent ban
+    fn expires(&self) -> Result<Option<i64>, Error> {
+        let duration = match (&self.duration_unit, self.duration) {
+            (BanDurationUnit::Permanent, _) => return Ok(None),
+            (BanDurationUnit::Hours, Some(d)) if d > 0 && d <= MAX_BAN_DAYS * 24 => {
+                Duration::hours(d)
+            }
+            (BanDurationUnit::Days, Some(d)) if d > 0 && d <= MAX_BAN_DAYS => Duration::days(d),
+            _ => {
+                return Err(anyhow!(
+                    "Ban duration needs to be a positive number of at most {MAX_BAN_DAYS} days"
+                ))
+            }
+        };
+        let expires = Utc::now()
+            .checked_add_signed(duration)
+            .ok_or_else(|| anyhow!("Invalid ban duration"))?;
+        Ok(Some(expires.timestamp()))
+    }
 }
 
 #[post("/ban_user", data = "<form>")]
 pub async fn ban(form: Form<BanUserForm>, site_data: SiteData) -> Result<Redirect, ErrorPage> {
     let auth = site_data.auth.clone().unwrap();
-    ban_user(form.user_id, form.reason.clone(), form.remove_data, auth).await?;
-    Ok(Redirect::to(uri!(view_profile(u = form.user_id))))
+    let reason = empty_to_opt(form.reason.clone());
+    let expires = form.expires()?;
+    match form.community_id {
+        Some(community_id) => {
+            ban_from_community(
+                CommunityId(community_id),
+                PersonId(form.user_id),
+                true,
+                reason,
+                form.remove_data,
+                expires,
+                auth,
+            )
+            .await?;
+            Ok(Redirect::to(uri!(forum_bans(community_id, _))))
+        }
+        None => {
+            ban_user(form.user_id, true, reason, form.remove_data, expires, auth).await?;
+            Ok(Redirect::to(uri!(view_profile(u = form.user_id))))
+        }
+    }
+}
+
+/// Topics and posts which the user has bookmarked
+#[get("/bookmarks?<page>")]
+pub async fn bookmarks(
+    page: Option<i32>,
+    site_data: SiteData,
+) -> Result<Either<Template, Redirect>, ErrorPage> {
+    let (person_id, auth) = match (&site_data.site.my_user, &site_data.auth) {
+        (Some(my_user), Some(auth)) => (my_user.local_user_view.person.id, auth.clone()),
+        _ => return Ok(Either::Right(Redirect::to(uri!(login)))),
+    };
+    let page = page.unwrap_or(1);
+    let saved = get_saved(person_id, page, site_data.topics_per_page, auth).await?;
+    let fetched_count = saved.posts.len().max(saved.comments.len());
+    let limit = PageLimit::Unknown(fetched_count, site_data.topics_per_page);
+    let pagination = Pagination::new(page, limit, "/bookmarks?");
+    let posts = saved.posts;
+    let comments = saved.comments;
+    let ctx = Context::builder()
+        .title(format!(
+            "Bookmarks - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { posts, comments, pagination })
+        .build();
+    Ok(Either::Left(Template::render("user/bookmarks", ctx)))
+}
+
+#[derive(FromForm)]
+pub struct BookmarkForm {
+    t: Option<i32>,
+    r: Option<i32>,
+    save: bool,
+    /// Go back to the bookmarks page instead of the topic
+    return_to_bookmarks: bool,
+}
+
+#[post("/bookmark", data = "<form>")]
+pub async fn bookmark(
+    form: Form<BookmarkForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let auth = site_data.auth.clone().unwrap();
+    let link = match (form.t, form.r) {
+        (Some(t), None) => {
+            save_post(t, form.save, auth).await?;
+            format!("/view_topic?t={t}#p1")
+        }
+        (None, Some(r)) => {
+            save_comment(r, form.save, auth).await?;
+            format!("/viewtopic?p={r}")
+        }
+        _ => return Err(anyhow!("One of params t and r needs to be set").into()),
+    };
+    if form.return_to_bookmarks {
+        Ok(Redirect::to(uri!(bookmarks(_))))
+    } else {
+        Ok(Redirect::to(link))
+    }
+}
+
+/// Topics which the user is subscribed to
+#[get("/subscriptions")]
+pub async fn topic_subscriptions(site_data: SiteData) -> Either<Template, Redirect> {
+    let person_id = match &site_data.site.my_user {
+        Some(my_user) => my_user.local_user_view.person.id,
+        None => return Either::Right(Redirect::to(uri!(login))),
+    };
+    let topics = list_subscriptions(person_id);
+    let ctx = Context::builder()
+        .title(format!(
+            "Subscriptions - {}",
+            site_data.site.site_view.site.name
+        ))
+        .site_data(site_data)
+        .other(context! { topics })
+        .build();
+    Either::Left(Template::render("user/subscriptions", ctx))
+}
+
+#[derive(FromForm)]
+pub struct SubscribeTopicForm {
+    t: i32,
+    subscribe: bool,
+    /// Go back to the subscriptions page instead of the topic
+    return_to_subscriptions: bool,
+}
+
+#[post("/subscribe_topic", data = "<form>")]
+pub async fn subscribe(
+    form: Form<SubscribeTopicForm>,
+    site_data: SiteData,
+) -> Result<Redirect, ErrorPage> {
+    let (person_id, auth) = match (&site_data.site.my_user, site_data.auth) {
+        (Some(my_user), Some(auth)) => (my_user.local_user_view.person.id, auth),
+        _ => return Err(anyhow!("not_logged_in").into()),
+    };
+    let post_id = PostId(form.t);
+    if form.subscribe {
+        // only notify about replies which are written after subscribing
+        let (post, comments) = join(
+            get_post(form.t, Some(auth.clone())),
+            list_newest_comments(Some(post_id), None, 1, Some(auth)),
+        )
+        .await;
+        let last_comment = comments?.comments.first().map(|c| c.comment.id);
+        let title = post?.post_view.post.name;
+        subscribe_topic(person_id, post_id, title, last_comment)?;
+    } else {
+        unsubscribe_topic(person_id, post_id)?;
+    }
+    if form.return_to_subscriptions {
+        Ok(Redirect::to(uri!(topic_subscriptions)))
+    } else {
+        Ok(Redirect::to(format!("/view_topic?t={}", form.t)))
+    }
+}
+
+#[cfg(test)]
+fn test_ban_form(duration: Option<i64>, duration_unit: BanDurationUnit) -> BanUserForm {
+    BanUserForm {
+        user_id: 2,
+        community_id: None,
+        reason: String::new(),
+        remove_data: false,
+        duration,
+        duration_unit,
+    }
+}
+
+#[test]
+fn ban_expiry() {
+    let now = Utc::now().timestamp();
+    let permanent = test_ban_form(Some(3), BanDurationUnit::Permanent);
+    assert_eq!(None, permanent.expires().unwrap());
+    let hours = test_ban_form(Some(3), BanDurationUnit::Hours)
+        .expires()
+        .unwrap()
+        .unwrap();
+    assert!((hours - now - 3 * 3600).abs() < 60);
+    let days = test_ban_form(Some(2), BanDurationUnit::Days)
+        .expires()
+        .unwrap()
+        .unwrap();
+    assert!((days - now - 2 * 86400).abs() < 60);
+    let max = test_ban_form(Some(MAX_BAN_DAYS), BanDurationUnit::Days);
+    assert!(max.expires().is_ok());
+}
+
+#[test]
+fn ban_expiry_invalid_duration() {
+    for (duration, unit) in [
+        (None, BanDurationUnit::Days),
+        (Some(0), BanDurationUnit::Hours),
+        (Some(-1), BanDurationUnit::Days),
+        (Some(MAX_BAN_DAYS + 1), BanDurationUnit::Days),
+        (Some(MAX_BAN_DAYS * 24 + 1), BanDurationUnit::Hours),
+        // would overflow chrono durations
+        (Some(i64::MAX), BanDurationUnit::Days),
+        (Some(i64::MAX), BanDurationUnit::Hours),
+    ] {
+        assert!(test_ban_form(duration, unit).expires().is_err());
+    }
 }
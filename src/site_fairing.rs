@@ -1,16 +1,20 @@
 use crate::{
     api::{
-        extra::{get_notifications, Notification},
+        extra::{get_notifications, Notification, HEADER_NOTIFICATIONS},
         gen_request_url,
         handle_response,
-        private_message::list_private_messages,
+        site::get_registration_application_count,
+        user::{get_report_count, get_unread_count},
         CLIENT,
     },
+    env::{posts_per_page, topics_per_page},
     routes::auth,
+    subscriptions::{subscription_notification_count, subscription_notifications},
+    user_prefs::get_user_prefs,
 };
 use anyhow::Error;
 use chrono::Local;
-use futures::future::join;
+use futures::future::join4;
 use itertools::Itertools;
 use lemmy_api_common::{
     sensitive::Sensitive,
@@ -67,12 +71,23 @@ impl<'r> FromRequest<'r> for SiteData {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SiteData {
     pub site: GetSiteResponse,
+    /// Newest unread notifications, shown in the header
     pub notifications: Vec<Notification>,
-    pub unread_pm_count: usize,
+    /// Total number of unread mentions, replies and new replies in subscribed topics
+    pub notification_count: i64,
+    pub unread_pm_count: i64,
     pub current_date_time: String,
     pub auth: Option<Sensitive<String>>,
     pub lang: String,
     pub lemmybb_version: String,
+    /// Effective number of topics per page, from user preferences or admin config
+    pub topics_per_page: i32,
+    /// Effective number of posts per page in topics, from user preferences or admin config
+    pub posts_per_page: i32,
+    /// Number of unresolved reports, only set for moderators and admins
+    pub report_count: i64,
+    /// Number of unhandled registration applications, only set for admins
+    pub registration_application_count: i64,
 }
 
 async fn get_site_data(request: &Request<'_>) -> Result<SiteData, Error> {
@@ -118,10 +133,29 @@ async fn get_site_data(request: &Request<'_>) -> Result<SiteData, Error> {
         }
         None => browser_lang,
     };
+    let prefs = site
+        .my_user
+        .as_ref()
+        .map(|u| get_user_prefs(u.local_user_view.person.id))
+        .unwrap_or_default();
+    // values from environment are already limited, and user preferences when they are saved
+    let topics_per_page = prefs.topics_per_page.unwrap_or_else(topics_per_page);
+    let posts_per_page = prefs.posts_per_page.unwrap_or_else(posts_per_page);
+    let is_admin = site
+        .my_user
+        .as_ref()
+        .map(|u| u.local_user_view.person.admin)
+        .unwrap_or(false);
+    let is_moderator = site
+        .my_user
+        .as_ref()
+        .map(|u| u.local_user_view.person.admin || !u.moderates.is_empty())
+        .unwrap_or(false);
 
     let mut site_data = SiteData {
         site,
         notifications: vec![],
+        notification_count: 0,
         // TODO: why is this?
         unread_pm_count: 0,
         current_date_time: Local::now().naive_local().format("%a %v %R").to_string(),
@@ -130,15 +164,52 @@ async fn get_site_data(request: &Request<'_>) -> Result<SiteData, Error> {
         lemmybb_version: option_env!("LEMMYBB_VERSION")
             .unwrap_or("unknown version")
             .to_string(),
+        topics_per_page,
+        posts_per_page,
+        report_count: 0,
+        registration_application_count: 0,
     };
     if let Some(auth) = auth {
-        let (notifications, private_messages) = join(
+        let report_count = async {
+            match is_moderator {
+                true => get_report_count(auth.clone())
+                    .await
+                    .map(|r| r.post_reports + r.comment_reports),
+                false => Ok(0),
+            }
+        };
+        let registration_application_count = async {
+            match is_admin {
+                true => get_registration_application_count(auth.clone())
+                    .await
+                    .map(|r| r.registration_applications),
+                false => Ok(0),
+            }
+        };
+        let (notifications, unread_count, report_count, registration_application_count) = join4(
             get_notifications(auth.clone()),
-            list_private_messages(true, auth.clone()),
+            get_unread_count(auth.clone()),
+            report_count,
+            registration_application_count,
         )
         .await;
-        site_data.notifications = notifications?;
-        site_data.unread_pm_count = private_messages?.private_messages.len();
+        let mut notifications = notifications?;
+        let unread_count = unread_count?;
+        site_data.notification_count = unread_count.mentions + unread_count.replies;
+        if let Some(my_user) = &site_data.site.my_user {
+            let person_id = my_user.local_user_view.person.id;
+            site_data.notification_count += subscription_notification_count(person_id) as i64;
+            notifications.extend(subscription_notifications(
+                person_id,
+                1,
+                HEADER_NOTIFICATIONS,
+            ));
+            notifications.sort_by_key(|n| n.time);
+        }
+        site_data.notifications = notifications;
+        site_data.unread_pm_count = unread_count.private_messages;
+        site_data.report_count = report_count?;
+        site_data.registration_application_count = registration_application_count?;
     }
     Ok(site_data)
 }
@@ -156,10 +227,15 @@ pub async fn test_site_data(auth: Option<Sensitive<String>>) -> SiteData {
     SiteData {
         site,
         notifications: vec![],
+        notification_count: 0,
         unread_pm_count: 0,
         current_date_time: "".to_string(),
         auth,
         lang: "".to_string(),
         lemmybb_version: "".to_string(),
+        topics_per_page: topics_per_page(),
+        posts_per_page: posts_per_page(),
+        report_count: 0,
+        registration_application_count: 0,
     }
 }
@@ -0,0 +1,251 @@
+use crate::{
+    api::{
+        comment::list_newest_comments,
+        extra::{Notification, NotificationKind},
+    },
+    persist,
+};
+use anyhow::Error;
+use futures::future::join_all;
+use lemmy_api_common::lemmy_db_schema::newtypes::{CommentId, PersonId, PostId};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+use tokio::time::sleep;
+
+pub static SUBSCRIPTIONS_FILE: &str = "lemmybb_subscriptions.json";
+
+/// How often subscribed topics are checked for new replies.
+static POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of comments which are fetched per topic on each poll.
+static POLL_ITEMS: i64 = 50;
+
+/// Highest number of unseen notifications which are kept per user, older ones are dropped.
+static MAX_NOTIFICATIONS: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopicSubscription {
+    pub post_id: PostId,
+    pub title: String,
+    /// Highest comment id in the topic which the user was already notified about
+    last_comment: CommentId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SubscriptionNotification {
+    post_id: PostId,
+    notification: Notification,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct PersonSubscriptions {
+    #[serde(default)]
+    topics: HashMap<PostId, TopicSubscription>,
+    /// New replies in subscribed topics which the user hasn't seen yet
+    #[serde(default)]
+    notifications: Vec<SubscriptionNotification>,
+}
+
+static SUBSCRIPTIONS: Lazy<RwLock<HashMap<PersonId, PersonSubscriptions>>> =
+    Lazy::new(|| RwLock::new(persist::load(SUBSCRIPTIONS_FILE).unwrap_or_default()));
+
+/// Set when notifications were changed and need to be saved.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+fn save_subscriptions() -> Result<(), Error> {
+    persist::save(SUBSCRIPTIONS_FILE, &*SUBSCRIPTIONS.read().unwrap())
+}
+
+/// Subscribe user to new replies in the topic. `last_comment` is the newest comment which
+/// currently exists in the topic, the user is only notified about comments after it.
+pub fn subscribe_topic(
+    person_id: PersonId,
+    post_id: PostId,
+    title: String,
+    last_comment: Option<CommentId>,
+) -> Result<(), Error> {
+    let subscription = TopicSubscription {
+        post_id,
+        title,
+        last_comment: last_comment.unwrap_or(CommentId(0)),
+    };
+    SUBSCRIPTIONS
+        .write()
+        .unwrap()
+        .entry(person_id)
+        .or_default()
+        .topics
+        .insert(post_id, subscription);
+    save_subscriptions()
+}
+
+pub fn unsubscribe_topic(person_id: PersonId, post_id: PostId) -> Result<(), Error> {
+    if let Some(s) = SUBSCRIPTIONS.write().unwrap().get_mut(&person_id) {
+        s.topics.remove(&post_id);
+        s.notifications.retain(|n| n.post_id != post_id);
+    }
+    save_subscriptions()
+}
+
+pub fn is_subscribed(person_id: PersonId, post_id: PostId) -> bool {
+    SUBSCRIPTIONS
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .map(|s| s.topics.contains_key(&post_id))
+        .unwrap_or(false)
+}
+
+/// All topics which the user is subscribed to, sorted by title
+pub fn list_subscriptions(person_id: PersonId) -> Vec<TopicSubscription> {
+    let mut topics: Vec<_> = SUBSCRIPTIONS
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .map(|s| s.topics.values().cloned().collect())
+        .unwrap_or_default();
+    topics.sort_by(|a, b| a.title.cmp(&b.title));
+    topics
+}
+
+/// Notifications for new replies in subscribed topics, newest first, with the same pagination as
+/// other notification types.
+pub fn subscription_notifications(person_id: PersonId, page: i32, limit: i32) -> Vec<Notification> {
+    SUBSCRIPTIONS
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .map(|s| {
+            s.notifications
+                .iter()
+                .rev()
+                .skip(((page.max(1) - 1) * limit) as usize)
+                .take(limit as usize)
+                .map(|n| n.notification.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn subscription_notification_count(person_id: PersonId) -> usize {
+    SUBSCRIPTIONS
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .map(|s| s.notifications.len())
+        .unwrap_or(0)
+}
+
+/// Remove notifications for new replies in the given topic, or in all topics if `post_id` is
+/// None.
+pub fn clear_subscription_notifications(person_id: PersonId, post_id: Option<PostId>) {
+    if let Some(s) = SUBSCRIPTIONS.write().unwrap().get_mut(&person_id) {
+        let count = s.notifications.len();
+        s.notifications
+            .retain(|n| post_id.map(|p| p != n.post_id).unwrap_or(false));
+        if s.notifications.len() != count {
+            CHANGED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Remove the notification for a single new reply.
+pub fn mark_subscription_notification_read(person_id: PersonId, comment_id: CommentId) {
+    if let Some(s) = SUBSCRIPTIONS.write().unwrap().get_mut(&person_id) {
+        let count = s.notifications.len();
+        s.notifications
+            .retain(|n| n.notification.id != comment_id.0);
+        if s.notifications.len() != count {
+            CHANGED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn update_subscriptions() {
+    let post_ids: Vec<PostId> = SUBSCRIPTIONS
+        .read()
+        .unwrap()
+        .values()
+        .flat_map(|s| s.topics.keys().copied())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let comments = join_all(
+        post_ids
+            .iter()
+            .map(|p| list_newest_comments(Some(*p), None, POLL_ITEMS, None)),
+    )
+    .await;
+    let mut subscriptions = SUBSCRIPTIONS.write().unwrap();
+    for (post_id, comments) in post_ids.iter().zip(comments) {
+        let comments = match comments {
+            Ok(c) => c.comments,
+            Err(e) => {
+                warn!(
+                    "Failed to fetch comments for subscribed topic {}: {e:#}",
+                    post_id.0
+                );
+                continue;
+            }
+        };
+        let newest = match comments.iter().map(|c| c.comment.id).max_by_key(|c| c.0) {
+            Some(newest) => newest,
+            None => continue,
+        };
+        for (person_id, s) in subscriptions.iter_mut() {
+            let topic = match s.topics.get_mut(post_id) {
+                Some(topic) => topic,
+                None => continue,
+            };
+            let new_comments = comments.iter().filter(|c| {
+                c.comment.id.0 > topic.last_comment.0
+                    && c.creator.id != *person_id
+                    && !c.comment.deleted
+                    && !c.comment.removed
+            });
+            for c in new_comments {
+                s.notifications.push(SubscriptionNotification {
+                    post_id: *post_id,
+                    notification: Notification {
+                        kind: NotificationKind::Subscription,
+                        id: c.comment.id.0,
+                        read: false,
+                        title: "New reply in subscribed topic".to_string(),
+                        from_user: c.creator.clone(),
+                        reference: c.comment.content.clone(),
+                        time: c.comment.published,
+                        link: format!("/viewtopic?p={}", c.comment.id.0),
+                    },
+                });
+            }
+            if newest.0 > topic.last_comment.0 {
+                topic.last_comment = newest;
+                CHANGED.store(true, Ordering::Relaxed);
+            }
+            let excess = s.notifications.len().saturating_sub(MAX_NOTIFICATIONS);
+            s.notifications.drain(..excess);
+        }
+    }
+}
+
+/// Check subscribed topics for new replies in regular intervals, and write changes to disk.
+pub async fn poll_subscriptions() {
+    loop {
+        sleep(POLL_INTERVAL).await;
+        update_subscriptions().await;
+        if CHANGED.swap(false, Ordering::Relaxed) {
+            if let Err(e) = save_subscriptions() {
+                error!("Failed to save {SUBSCRIPTIONS_FILE}: {e:#}");
+                CHANGED.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
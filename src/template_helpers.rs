@@ -1,13 +1,9 @@
-use crate::{pagination::PAGE_ITEMS, site_fairing::SiteData};
+use crate::site_fairing::SiteData;
 use chrono::NaiveDateTime;
 use comrak::ComrakOptions;
 use json_gettext::{JSONGetText, JSONGetTextBuilder};
 use lemmy_api_common::{
-    lemmy_db_schema::{
-        newtypes::CommentId,
-        source::{community::CommunitySafe, person::PersonSafe},
-    },
-    lemmy_db_views::structs::CommentView,
+    lemmy_db_schema::source::{community::CommunitySafe, person::PersonSafe},
     lemmy_db_views_actor::structs::CommunityModeratorView,
 };
 use once_cell::sync::{Lazy, OnceCell};
@@ -49,17 +45,6 @@ handlebars_helper!(modulo: |a: i32, b: i32| {
     a % b
 });
 
-// Returns position of comment in thread. vec is assumed to be sorted
-handlebars_helper!(comment_page: |comment_id: CommentId, comments: Vec<CommentView>| {
-    let index = comments.iter().position(|c| c.comment.id == comment_id);
-    if let Some(i) = index {
-    (i as f32 / PAGE_ITEMS as f32).ceil() as i32
-        } else {
-        // TODO: properly handle case of deleted parent
-        -1
-    }
-});
-
 // Converts markdown to html. Replace generated <p></p> with <br /><br /> for newlines, because
 // otherwise fonts are rendered too big.
 handlebars_helper!(markdown: |md: Option<String>| {
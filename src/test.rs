@@ -5,10 +5,12 @@ use crate::{
         site::create_site,
     },
     init_rocket,
-    routes::{community::*, post::*, site::*, user::*},
+    routes::{admin::*, community::*, moderation::*, post::*, site::*, user::*},
     site_fairing::test_site_data,
 };
+use anyhow::Error;
 use lemmy_api_common::{
+    community::{CommunityResponse, CreateCommunity},
     lemmy_db_schema::source::local_site::RegistrationMode,
     sensitive::Sensitive,
     site::GetSiteResponse,
@@ -16,7 +18,7 @@ use lemmy_api_common::{
 use log::LevelFilter;
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::StatusCode;
-use rocket::{form::Form, local::asynchronous};
+use rocket::{form::Form, local::asynchronous, response::Responder};
 use serial_test::serial;
 use std::{env, future::Future, path::Path, time::Duration};
 use tokio::{
@@ -101,6 +103,19 @@ async fn init_backend() -> Sensitive<String> {
     auth
 }
 
+async fn create_test_community(
+    name: &str,
+    auth: Sensitive<String>,
+) -> Result<CommunityResponse, Error> {
+    let params = CreateCommunity {
+        name: name.to_string(),
+        title: name.to_string(),
+        auth,
+        ..Default::default()
+    };
+    create_community(params).await
+}
+
 #[actix_rt::test]
 #[serial]
 async fn index() {
@@ -139,16 +154,38 @@ async fn setup() {
 #[serial]
 async fn view_forum() {
     run_test(|client, auth| async move {
-        let created = create_community("my_community".to_string(), auth.clone())
+        let created = create_test_community("my_community", auth.clone())
             .await
             .unwrap();
         let f = created.community_view.community.id.0;
         let res = client
-            .get(uri!(view_forum(f, None::<i32>, None::<String>)))
+            .get(uri!(view_forum(f, None::<i32>, None::<String>, _, _)))
             .dispatch()
             .await;
         assert_eq!(200, res.status().code);
-        delete_community(created.community_view.community.id, auth.clone())
+        delete_community(created.community_view.community.id, true, auth.clone())
+            .await
+            .unwrap();
+    })
+    .await;
+}
+
+#[actix_rt::test]
+#[serial]
+async fn forum_moderation_pages() {
+    run_test(|client, auth| async move {
+        let created = create_test_community("my_community_3", auth.clone())
+            .await
+            .unwrap();
+        let f = created.community_view.community.id.0;
+        let res = client.get(uri!(forum_moderators(f))).dispatch().await;
+        assert_eq!(200, res.status().code);
+        let res = client.get(uri!(forum_bans(f, _))).dispatch().await;
+        assert_eq!(200, res.status().code);
+        // settings are only shown to moderators, like the creator of the forum
+        let site_data = test_site_data(Some(auth.clone())).await;
+        assert!(forum_settings(f, site_data).await.is_ok());
+        delete_community(created.community_view.community.id, true, auth.clone())
             .await
             .unwrap();
     })
@@ -159,7 +196,7 @@ async fn view_forum() {
 #[serial]
 async fn view_topic() {
     run_test(|_client, auth| async move {
-        let community = create_community("my_community_2".to_string(), auth.clone())
+        let community = create_test_community("my_community_2", auth.clone())
             .await
             .unwrap()
             .community_view
@@ -175,7 +212,7 @@ async fn view_topic() {
             .unwrap();
         assert!(post.right().is_some());
 
-        delete_community(community.id, auth).await.unwrap();
+        delete_community(community.id, true, auth).await.unwrap();
     })
     .await;
 }
@@ -190,6 +227,46 @@ async fn login() {
     .await;
 }
 
+#[actix_rt::test]
+#[serial]
+async fn legal() {
+    run_test(|client, _auth| async move {
+        let res = client.get(uri!(legal)).dispatch().await;
+        assert_eq!(200, res.status().code);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+#[serial]
+async fn site_settings_registration_mode() {
+    run_test(|client, auth| async move {
+        let mut site_data = test_site_data(Some(auth)).await;
+        // only the first registered user is admin, so the page is rendered with admin rights
+        site_data
+            .site
+            .my_user
+            .as_mut()
+            .unwrap()
+            .local_user_view
+            .person
+            .admin = true;
+        let selected = match site_data.site.site_view.local_site.registration_mode {
+            RegistrationMode::Open => "Open",
+            RegistrationMode::RequireApplication => "RequireApplication",
+            RegistrationMode::Closed => "Closed",
+        };
+        let template = site_settings(site_data).await.unwrap();
+        let req = client.get(uri!(site_settings));
+        let mut res = template.respond_to(req.inner()).unwrap();
+        let body = res.body_mut().to_string().await.unwrap();
+        assert!(body.contains(&format!(
+            r#"<option value="{selected}" selected="selected">"#
+        )));
+    })
+    .await;
+}
+
 #[actix_rt::test]
 #[serial]
 async fn register() {
@@ -223,6 +300,17 @@ async fn view_profile() {
     .await;
 }
 
+#[actix_rt::test]
+#[serial]
+async fn notifications_page() {
+    run_test(|_client, auth| async move {
+        let site_data = test_site_data(Some(auth)).await;
+        let res = notifications(None, Some(true), site_data).await.unwrap();
+        assert!(res.left().is_some());
+    })
+    .await;
+}
+
 #[actix_rt::test]
 #[serial]
 async fn image() {
@@ -0,0 +1,41 @@
+use crate::{forum_sort::ForumSort, persist};
+use anyhow::Error;
+use lemmy_api_common::lemmy_db_schema::newtypes::PersonId;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::RwLock};
+
+pub static USER_PREFS_FILE: &str = "lemmybb_user_prefs.json";
+
+/// Settings which are specific to lemmyBB, so they can't be stored in Lemmy
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct UserPrefs {
+    /// Overrides number of topics per page which is set by admin
+    pub topics_per_page: Option<i32>,
+    /// Overrides number of posts per page in topics which is set by admin
+    pub posts_per_page: Option<i32>,
+    /// Last selected order of topics in forums
+    pub forum_sort: Option<ForumSort>,
+    /// Last selected age filter for topics in forums, in days
+    pub forum_days: Option<i64>,
+}
+
+static USER_PREFS: Lazy<RwLock<HashMap<PersonId, UserPrefs>>> =
+    Lazy::new(|| RwLock::new(persist::load(USER_PREFS_FILE).unwrap_or_default()));
+
+pub fn get_user_prefs(person_id: PersonId) -> UserPrefs {
+    USER_PREFS
+        .read()
+        .unwrap()
+        .get(&person_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Store preferences of user, and write all preferences to disk. These are rarely changed, so
+/// there is no need to batch writes.
+pub fn save_user_prefs(person_id: PersonId, prefs: UserPrefs) -> Result<(), Error> {
+    let mut all_prefs = USER_PREFS.write().unwrap();
+    all_prefs.insert(person_id, prefs);
+    persist::save(USER_PREFS_FILE, &*all_prefs)
+}
@@ -1,5 +1,8 @@
 use crate::site_fairing::SiteData;
-use lemmy_api_common::site::GetSiteResponse;
+use lemmy_api_common::{
+    lemmy_db_views_actor::structs::CommunityModeratorView,
+    site::GetSiteResponse,
+};
 use serde::Serialize;
 use typed_builder::TypedBuilder;
 
@@ -58,6 +61,41 @@ pub struct Context<T: Into<String>, R: Serialize> {
     other: R,
 }
 
+/// Returns true if the logged in user is one of the site admins
+pub fn is_admin(site_data: &SiteData) -> bool {
+    match &site_data.site.my_user {
+        Some(my_user) => {
+            let my_id = my_user.local_user_view.person.id;
+            site_data.site.admins.iter().any(|a| a.person.id == my_id)
+        }
+        None => false,
+    }
+}
+
+/// Returns true if the logged in user is allowed to create new communities
+pub fn can_create_community(site_data: &SiteData) -> bool {
+    site_data.site.my_user.is_some()
+        && (is_admin(site_data)
+            || !site_data
+                .site
+                .site_view
+                .local_site
+                .community_creation_admin_only)
+}
+
+/// Returns true if the logged in user is admin or moderator of the community
+pub fn can_moderate(site_data: &SiteData, moderators: &[CommunityModeratorView]) -> bool {
+    site_data
+        .site
+        .my_user
+        .as_ref()
+        .map(|u| {
+            let person = &u.local_user_view.person;
+            person.admin || moderators.iter().any(|m| m.moderator.id == person.id)
+        })
+        .unwrap_or(false)
+}
+
 pub fn main_site_title(site: &GetSiteResponse) -> String {
     let site = &site.site_view.site;
     if let Some(description) = &site.description {